@@ -0,0 +1,605 @@
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use cairo_lang_runner::Arg;
+use cairo_lang_sierra::ids::ConcreteTypeId;
+use cairo_lang_sierra::program::{GenericArg, Program, TypeDeclaration};
+use num_bigint::{BigInt, BigUint, Sign};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::profiler;
+
+/// Shape of a parameter type, as far as the serialization is concerned.
+pub enum ArgType {
+    Felt,
+    Integer { bits: u32, signed: bool },
+    Bool,
+    U256,
+    ByteArray,
+    Array(Box<ArgType>),
+    Struct(Vec<ArgType>),
+}
+
+/// Value laid out the same way as in Cairo.
+enum CairoValue {
+    Felt(BigInt),
+    Array(Vec<CairoValue>),
+    Struct(Vec<CairoValue>),
+}
+
+/// Argument written as a Cairo literal on the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    /// Number or `'short string'`.
+    Felt(BigInt),
+    Bool(bool),
+    /// `"byte array"`.
+    String(String),
+    Array(Vec<Literal>),
+    Tuple(Vec<Literal>),
+}
+
+impl Literal {
+    /// Convert to the typed arguments representation.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Literal::Felt(felt) => Value::String(felt.to_string()),
+            Literal::Bool(value) => Value::Bool(*value),
+            Literal::String(value) => Value::String(value.clone()),
+            Literal::Array(items) | Literal::Tuple(items) => {
+                Value::Array(items.iter().map(Literal::to_json).collect())
+            }
+        }
+    }
+
+    fn to_cairo_value(&self) -> CairoValue {
+        match self {
+            Literal::Felt(felt) => CairoValue::Felt(felt.clone()),
+            Literal::Bool(value) => CairoValue::Felt(BigInt::from(u8::from(*value))),
+            Literal::String(value) => byte_array(value),
+            Literal::Array(items) => {
+                CairoValue::Array(items.iter().map(Literal::to_cairo_value).collect())
+            }
+            Literal::Tuple(items) => {
+                CairoValue::Struct(items.iter().map(Literal::to_cairo_value).collect())
+            }
+        }
+    }
+}
+
+/// Parse comma separated Cairo literals: decimal or hex numbers (possibly negative),
+/// `'short strings'`, `"byte arrays"`, `true`/`false`, `[arrays]` and `(tuples)`.
+pub fn parse_literals(input: &str) -> Result<Vec<Literal>> {
+    if input.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut parser = LiteralParser { input, pos: 0 };
+    parser
+        .list(None)
+        .with_context(|| format!("invalid arguments `{input}`"))
+}
+
+/// Parse a single felt literal, a decimal or hex number or a `'short string'`.
+pub fn parse_felt_literal(input: &str) -> Result<BigInt> {
+    match <[Literal; 1]>::try_from(parse_literals(input)?) {
+        Ok([Literal::Felt(felt)]) => Ok(felt),
+        _ => bail!("expected a felt, got `{input}`"),
+    }
+}
+
+/// Parse `name=value` pairs and order the values according to the parameter names,
+/// every parameter has to be given exactly once.
+pub fn named_literals(param_names: &[String], named_args: &[String]) -> Result<Vec<Literal>> {
+    let mut values: HashMap<&str, Literal> = HashMap::new();
+    for named_arg in named_args {
+        let (name, value) = named_arg
+            .split_once('=')
+            .with_context(|| format!("expected `name=value`, got `{named_arg}`"))?;
+        let name = name.trim();
+        ensure!(
+            param_names.iter().any(|param| param == name),
+            "unknown parameter `{name}`, expected one of: {}",
+            param_names.join(", ")
+        );
+        let [literal] = <[Literal; 1]>::try_from(parse_literals(value)?)
+            .map_err(|_| anyhow!("expected a single value for `{name}`, got `{value}`"))?;
+        ensure!(
+            values.insert(name, literal).is_none(),
+            "parameter `{name}` is given more than once"
+        );
+    }
+    param_names
+        .iter()
+        .map(|name| {
+            values
+                .remove(name.as_str())
+                .with_context(|| format!("missing parameter `{name}`"))
+        })
+        .collect()
+}
+
+/// Encode literals for the function without looking at its signature.
+/// Executable arguments are serialized with Cairo Serde, other functions take them as is.
+pub fn literal_args(program: &Program, function: &str, literals: &[Literal]) -> Result<Vec<Arg>> {
+    let entrypoint = profiler::find_function(program, function)?;
+    let values = literals.iter().map(Literal::to_cairo_value).collect();
+    Ok(encode(values, profiler::is_executable_wrapper(entrypoint)))
+}
+
+/// Serialize literals with Cairo Serde, e.g. `[1, 2]` becomes `2, 1, 2`.
+pub fn serialized_args(literals: &[Literal]) -> Vec<Arg> {
+    encode(literals.iter().map(Literal::to_cairo_value).collect(), true)
+}
+
+/// Encode values of the function parameters according to its signature.
+/// Executable arguments are serialized with Cairo Serde, other functions take them as is.
+///
+/// Felts and integers are numbers or (hex) strings, `u256` can also be a number,
+/// `ByteArray` is a string, arrays and tuples are JSON arrays, structs are JSON arrays
+/// or objects with members in the declaration order.
+pub fn typed_args(program: &Program, function: &str, values: Vec<Value>) -> Result<Vec<Arg>> {
+    let param_types = param_types(program, function)?;
+    ensure!(
+        param_types.len() == values.len(),
+        "`{function}` expects {} arguments, got {}",
+        param_types.len(),
+        values.len()
+    );
+
+    let values = param_types
+        .iter()
+        .zip(values)
+        .map(|(ty, value)| parse_value(ty, value))
+        .collect::<Result<Vec<_>>>()?;
+    let entrypoint = profiler::find_function(program, function)?;
+    Ok(encode(values, profiler::is_executable_wrapper(entrypoint)))
+}
+
+/// Types of the function parameters, for executables the wrapped function is inspected.
+pub fn param_types(program: &Program, function: &str) -> Result<Vec<ArgType>> {
+    let entrypoint = profiler::find_function(program, function)?;
+    let user_function = if profiler::is_executable_wrapper(entrypoint) {
+        profiler::executable_function(program, entrypoint).with_context(|| {
+            format!("signature of `{function}` not found, it might have been inlined")
+        })?
+    } else {
+        entrypoint
+    };
+
+    let resolver = TypeResolver::new(program);
+    let mut param_types = Vec::new();
+    for ty in &user_function.signature.param_types {
+        if !resolver.is_implicit(ty) {
+            param_types.push(resolver.resolve(ty)?);
+        }
+    }
+    Ok(param_types)
+}
+
+fn encode(values: Vec<CairoValue>, serialized: bool) -> Vec<Arg> {
+    let mut args = Vec::new();
+    if serialized {
+        let mut felts = Vec::new();
+        values.iter().for_each(|value| serialize(value, &mut felts));
+        args.extend(felts.iter().map(|felt| Arg::Value(felt.into())));
+    } else {
+        values
+            .into_iter()
+            .for_each(|value| push_args(value, &mut args));
+    }
+    args
+}
+
+struct LiteralParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl LiteralParser<'_> {
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.input.len() - self.rest().trim_start().len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Comma separated literals until the closing bracket or the end of input.
+    fn list(&mut self, close: Option<char>) -> Result<Vec<Literal>> {
+        let mut items = Vec::new();
+        if close.is_some_and(|close| self.eat(close)) {
+            return Ok(items);
+        }
+        loop {
+            items.push(self.literal()?);
+            if self.eat(',') {
+                continue;
+            }
+            match close {
+                Some(close) => ensure!(self.eat(close), "expected `{close}` at {}", self.pos),
+                None => {
+                    self.skip_whitespace();
+                    ensure!(self.rest().is_empty(), "unexpected `{}`", self.rest());
+                }
+            }
+            return Ok(items);
+        }
+    }
+
+    fn literal(&mut self) -> Result<Literal> {
+        if self.eat('[') {
+            return Ok(Literal::Array(self.list(Some(']'))?));
+        }
+        if self.eat('(') {
+            return Ok(Literal::Tuple(self.list(Some(')'))?));
+        }
+        if self.eat('\'') {
+            let value = self.quoted('\'')?;
+            ensure!(
+                value.len() <= 31,
+                "short string `{value}` is longer than 31 bytes"
+            );
+            return Ok(Literal::Felt(BigInt::from_bytes_be(
+                Sign::Plus,
+                value.as_bytes(),
+            )));
+        }
+        if self.eat('"') {
+            return Ok(Literal::String(self.quoted('"')?));
+        }
+
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .unwrap_or(self.rest().len());
+        let token = self.rest()[..len].replace('_', "");
+        self.pos += len;
+        match token.as_str() {
+            "true" => Ok(Literal::Bool(true)),
+            "false" => Ok(Literal::Bool(false)),
+            _ => {
+                let (negative, digits) = match token.strip_prefix('-') {
+                    Some(digits) => (true, digits),
+                    None => (false, token.as_str()),
+                };
+                let number = match digits.strip_prefix("0x") {
+                    Some(hex) => BigInt::parse_bytes(hex.as_bytes(), 16),
+                    None => BigInt::parse_bytes(digits.as_bytes(), 10),
+                }
+                .with_context(|| format!("invalid number `{token}` at {}", self.pos))?;
+                Ok(Literal::Felt(if negative { -number } else { number }))
+            }
+        }
+    }
+
+    /// String until the closing quote, supports `\\`-escaped characters.
+    fn quoted(&mut self, quote: char) -> Result<String> {
+        let input = self.input;
+        let mut value = String::new();
+        let mut chars = input[self.pos..].char_indices();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let (_, escaped) = chars.next().with_context(|| "unterminated string")?;
+                    value.push(if escaped == 'n' { '\n' } else { escaped });
+                }
+                c if c == quote => {
+                    self.pos += idx + c.len_utf8();
+                    return Ok(value);
+                }
+                c => value.push(c),
+            }
+        }
+        bail!("unterminated string")
+    }
+}
+
+struct TypeResolver<'a> {
+    declarations: HashMap<&'a ConcreteTypeId, &'a TypeDeclaration>,
+}
+
+impl<'a> TypeResolver<'a> {
+    fn new(program: &'a Program) -> Self {
+        Self {
+            declarations: program
+                .type_declarations
+                .iter()
+                .map(|declaration| (&declaration.id, declaration))
+                .collect(),
+        }
+    }
+
+    fn is_implicit(&self, ty: &ConcreteTypeId) -> bool {
+        self.declarations.get(ty).is_some_and(|declaration| {
+            profiler::IMPLICIT_TYPES.contains(&declaration.long_id.generic_id.0.as_str())
+        })
+    }
+
+    fn resolve(&self, ty: &ConcreteTypeId) -> Result<ArgType> {
+        let declaration = self
+            .declarations
+            .get(ty)
+            .with_context(|| format!("type `{ty}` is not declared"))?;
+        let long_id = &declaration.long_id;
+        let mut inner_types = long_id.generic_args.iter().filter_map(|arg| match arg {
+            GenericArg::Type(ty) => Some(ty),
+            _ => None,
+        });
+        let user_type = long_id.generic_args.iter().find_map(|arg| match arg {
+            GenericArg::UserType(user_type) => user_type.debug_name.as_deref(),
+            _ => None,
+        });
+
+        Ok(match long_id.generic_id.0.as_str() {
+            "felt252" | "ContractAddress" | "ClassHash" | "StorageAddress" => ArgType::Felt,
+            "bytes31" => ArgType::Integer {
+                bits: 248,
+                signed: false,
+            },
+            generic_id @ ("u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64"
+            | "i128") => ArgType::Integer {
+                bits: generic_id[1..].parse()?,
+                signed: generic_id.starts_with('i'),
+            },
+            "Snapshot" | "NonZero" => self.resolve(
+                inner_types
+                    .next()
+                    .with_context(|| format!("`{ty}` is empty"))?,
+            )?,
+            "Array" => ArgType::Array(Box::new(
+                self.resolve(
+                    inner_types
+                        .next()
+                        .with_context(|| format!("`{ty}` is empty"))?,
+                )?,
+            )),
+            "Struct" => match user_type {
+                Some("core::integer::u256") => ArgType::U256,
+                Some("core::byte_array::ByteArray") => ArgType::ByteArray,
+                _ => ArgType::Struct(
+                    inner_types
+                        .map(|ty| self.resolve(ty))
+                        .collect::<Result<_>>()?,
+                ),
+            },
+            "Enum" if user_type == Some("core::bool") => ArgType::Bool,
+            generic_id => bail!("arguments of type `{ty}` ({generic_id}) are not supported"),
+        })
+    }
+}
+
+fn parse_value(ty: &ArgType, value: Value) -> Result<CairoValue> {
+    Ok(match (ty, value) {
+        (ArgType::Felt, value) => CairoValue::Felt(parse_felt(&value)?),
+        (&ArgType::Integer { bits, signed }, value) => {
+            let int = parse_felt(&value)?;
+            let (min, max) = integer_range(bits, signed);
+            let name = format!("{}{bits}", if signed { 'i' } else { 'u' });
+            ensure!(
+                min <= int && int <= max,
+                "`{value}` is out of the range of `{name}`, {min} to {max}"
+            );
+            CairoValue::Felt(field_element(int))
+        }
+        (ArgType::Bool, Value::Bool(value)) => CairoValue::Felt(BigInt::from(u8::from(value))),
+        (ArgType::U256, value @ (Value::Number(_) | Value::String(_))) => {
+            let value = parse_felt(&value)?
+                .to_biguint()
+                .with_context(|| "u256 value can't be negative")?;
+            let mask = (BigUint::from(1u8) << 128u32) - 1u8;
+            CairoValue::Struct(vec![
+                CairoValue::Felt(BigInt::from(&value & &mask)),
+                CairoValue::Felt(BigInt::from(value >> 128u32)),
+            ])
+        }
+        (ArgType::U256, value) => {
+            parse_value(&ArgType::Struct(vec![ArgType::Felt, ArgType::Felt]), value)?
+        }
+        (ArgType::ByteArray, Value::String(value)) => byte_array(&value),
+        (ArgType::Array(ty), Value::Array(items)) => CairoValue::Array(
+            items
+                .into_iter()
+                .map(|item| parse_value(ty, item))
+                .collect::<Result<_>>()?,
+        ),
+        (ArgType::Struct(types), Value::Array(items)) => parse_members(types, items)?,
+        (ArgType::Struct(types), Value::Object(fields)) => {
+            parse_members(types, fields.into_iter().map(|(_, value)| value).collect())?
+        }
+        (_, value) => bail!("unexpected argument value `{value}`"),
+    })
+}
+
+/// Smallest and largest value of an integer type.
+fn integer_range(bits: u32, signed: bool) -> (BigInt, BigInt) {
+    if signed {
+        let bound = BigInt::from(1u8) << (bits - 1);
+        (-bound.clone(), bound - 1u8)
+    } else {
+        (BigInt::from(0u8), (BigInt::from(1u8) << bits) - 1u8)
+    }
+}
+
+/// Felt of an integer, negative values wrap around the field prime as in Cairo.
+fn field_element(value: BigInt) -> BigInt {
+    if value.sign() == Sign::Minus {
+        let prime = (BigInt::from(1u8) << 251u32) + (BigInt::from(17u8) << 192u32) + 1u8;
+        value + prime
+    } else {
+        value
+    }
+}
+
+fn parse_members(types: &[ArgType], values: Vec<Value>) -> Result<CairoValue> {
+    ensure!(
+        types.len() == values.len(),
+        "expected {} members, got {}",
+        types.len(),
+        values.len()
+    );
+    Ok(CairoValue::Struct(
+        types
+            .iter()
+            .zip(values)
+            .map(|(ty, value)| parse_value(ty, value))
+            .collect::<Result<_>>()?,
+    ))
+}
+
+fn parse_felt(value: &Value) -> Result<BigInt> {
+    let felt = match value {
+        Value::Number(number) => number.to_string().parse().ok(),
+        Value::String(string) => match string.strip_prefix("0x") {
+            Some(hex) => BigInt::parse_bytes(hex.as_bytes(), 16),
+            None => string.parse().ok(),
+        },
+        Value::Bool(value) => Some(BigInt::from(u8::from(*value))),
+        _ => None,
+    };
+    felt.with_context(|| format!("invalid felt value `{value}`"))
+}
+
+/// Split the string into 31-byte words and the pending word, as `ByteArray` does.
+fn byte_array(value: &str) -> CairoValue {
+    let bytes = value.as_bytes();
+    let full_words_len = bytes.len() / 31 * 31;
+    let words = bytes[..full_words_len]
+        .chunks(31)
+        .map(|word| CairoValue::Felt(BigInt::from_bytes_be(Sign::Plus, word)))
+        .collect();
+    let pending_word = &bytes[full_words_len..];
+    CairoValue::Struct(vec![
+        CairoValue::Array(words),
+        CairoValue::Felt(BigInt::from_bytes_be(Sign::Plus, pending_word)),
+        CairoValue::Felt(BigInt::from(pending_word.len())),
+    ])
+}
+
+/// Cairo Serde: members one by one, arrays prefixed with the length.
+fn serialize(value: &CairoValue, felts: &mut Vec<BigInt>) {
+    match value {
+        CairoValue::Felt(felt) => felts.push(felt.clone()),
+        CairoValue::Struct(members) => members.iter().for_each(|member| serialize(member, felts)),
+        CairoValue::Array(items) => {
+            felts.push(BigInt::from(items.len()));
+            items.iter().for_each(|item| serialize(item, felts));
+        }
+    }
+}
+
+/// Runner arguments: structs are flattened, arrays are passed as segments.
+fn push_args(value: CairoValue, args: &mut Vec<Arg>) {
+    match value {
+        CairoValue::Felt(felt) => args.push(Arg::Value((&felt).into())),
+        CairoValue::Struct(members) => members
+            .into_iter()
+            .for_each(|member| push_args(member, args)),
+        CairoValue::Array(items) => {
+            let mut inner = Vec::new();
+            items
+                .into_iter()
+                .for_each(|item| push_args(item, &mut inner));
+            args.push(Arg::Array(inner));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_byte_array() {
+        let value = parse_value(&ArgType::ByteArray, Value::String("a".repeat(33))).unwrap();
+        let mut felts = Vec::new();
+        serialize(&value, &mut felts);
+        let word = BigInt::from_bytes_be(Sign::Plus, &[b'a'; 31]);
+        let pending_word = BigInt::from_bytes_be(Sign::Plus, b"aa");
+        assert_eq!(
+            felts,
+            vec![BigInt::from(1), word, pending_word, BigInt::from(2)]
+        );
+    }
+
+    #[test]
+    fn test_integer_ranges() {
+        let u8_type = ArgType::Integer { bits: 8, signed: false };
+        assert!(parse_value(&u8_type, Value::from(255)).is_ok());
+        let err = parse_value(&u8_type, Value::from(300)).err().unwrap().to_string();
+        assert_eq!(err, "`300` is out of the range of `u8`, 0 to 255");
+        let u64_type = ArgType::Integer { bits: 64, signed: false };
+        assert!(parse_value(&u64_type, Value::from(-1)).is_err());
+        let u128_type = ArgType::Integer { bits: 128, signed: false };
+        let above = Value::String(format!("{}", BigInt::from(1u8) << 128u32));
+        assert!(parse_value(&u128_type, above).is_err());
+
+        let i8_type = ArgType::Integer { bits: 8, signed: true };
+        assert!(parse_value(&i8_type, Value::from(128)).is_err());
+        let Ok(CairoValue::Felt(felt)) = parse_value(&i8_type, Value::from(-128)) else {
+            panic!("expected a felt");
+        };
+        assert_eq!(field_element(BigInt::from(-1)) - felt, BigInt::from(127u8));
+    }
+
+    #[test]
+    fn test_parse_literals() {
+        let literals = parse_literals("1, -0x2,'ab', [true, (3, \"c\")], []").unwrap();
+        assert_eq!(
+            literals,
+            vec![
+                Literal::Felt(BigInt::from(1)),
+                Literal::Felt(BigInt::from(-2)),
+                Literal::Felt(BigInt::from(0x6162)),
+                Literal::Array(vec![
+                    Literal::Bool(true),
+                    Literal::Tuple(vec![
+                        Literal::Felt(BigInt::from(3)),
+                        Literal::String("c".to_string())
+                    ])
+                ]),
+                Literal::Array(vec![]),
+            ]
+        );
+        assert!(parse_literals("[1, 2").is_err());
+        assert!(parse_literals("'unterminated").is_err());
+    }
+
+    #[test]
+    fn test_named_literals() {
+        let names = vec!["recipient".to_string(), "amounts".to_string()];
+        let args = vec!["amounts=[1, 2]".to_string(), "recipient=0xabc".to_string()];
+        assert_eq!(
+            named_literals(&names, &args).unwrap(),
+            vec![
+                Literal::Felt(BigInt::from(0xabc)),
+                Literal::Array(vec![
+                    Literal::Felt(BigInt::from(1)),
+                    Literal::Felt(BigInt::from(2))
+                ]),
+            ]
+        );
+        assert!(named_literals(&names, &args[..1]).is_err());
+        assert!(named_literals(&names, &["fee=1".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_serialize_u256() {
+        let value = parse_value(
+            &ArgType::U256,
+            Value::String("0x1".to_string() + &"0".repeat(32)),
+        )
+        .unwrap();
+        let mut felts = Vec::new();
+        serialize(&value, &mut felts);
+        assert_eq!(felts, vec![BigInt::from(0), BigInt::from(1)]);
+    }
+}
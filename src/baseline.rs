@@ -0,0 +1,70 @@
+use anyhow::{ensure, Context, Result};
+use camino::Utf8PathBuf;
+use std::fs;
+
+use crate::burnprofile::{self, BurnProfile};
+use crate::folded;
+use crate::output;
+use crate::report::Delta;
+
+/// Directory of the saved baselines, meant to be committed along with the package.
+const DIR: &str = ".burn/baseline";
+
+/// Burnprofile of the baseline of the given profile, e.g. of a package entrypoint.
+pub fn path(name: &str) -> Utf8PathBuf {
+    Utf8PathBuf::from(DIR).join(format!("{}.burnprofile", output::file_stem(name)))
+}
+
+/// Save the folded profile as the baseline of the given profile, with its weight model.
+pub fn save(name: &str, folded: &str, weight: Option<String>) -> Result<Utf8PathBuf> {
+    let path = path(name);
+    fs::create_dir_all(DIR).with_context(|| format!("failed to create {DIR}"))?;
+    burnprofile::write(&BurnProfile::new(folded, None, None, weight), &path)
+        .with_context(|| format!("failed to save baseline: {path}"))?;
+    Ok(path)
+}
+
+/// Folded stacks of the baseline of the given profile, `None` if it was not saved. A baseline
+/// of another weight model than the run is refused, as the totals can't be compared.
+pub fn load(name: &str, weight: Option<&str>) -> Result<Option<String>> {
+    let path = path(name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("failed to read baseline: {path}"))?;
+    let profile =
+        burnprofile::parse(&contents).with_context(|| format!("invalid baseline: {path}"))?;
+    if let (Some(saved), Some(weight)) = (profile.weight.as_deref(), weight) {
+        ensure!(
+            saved == weight,
+            "the baseline {path} weighs {saved} and the run {weight}, \
+            save a baseline of this weight model with `--save-baseline`"
+        );
+    }
+    Ok(Some(profile.root.folded()))
+}
+
+/// Change of the total weight relative to the baseline, e.g. `+120 steps (+2.50%)`.
+pub fn change(baseline: &str, folded: &str, unit: &str) -> String {
+    let delta = Delta {
+        base: folded::total_weight(baseline),
+        current: folded::total_weight(folded),
+    };
+    let change = delta.current as i64 - delta.base as i64;
+    format!("{change:+} {unit} ({:+.2}%)", delta.percent())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_baseline() {
+        assert_eq!(path("pkg::foo"), ".burn/baseline/pkg__foo.burnprofile");
+        let current = "main;jump 90\nmain;return 15";
+        assert_eq!(change("main;jump 100", current, "steps"), "+5 steps (+5.00%)");
+        assert_eq!(change("main;jump 100", "main;jump 80", "steps"), "-20 steps (-20.00%)");
+        assert_eq!(change("main;jump 100", "main;jump 100", "steps"), "+0 steps (+0.00%)");
+    }
+}
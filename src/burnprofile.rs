@@ -0,0 +1,178 @@
+use anyhow::{ensure, Context, Result};
+use camino::Utf8PathBuf;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::folded;
+use crate::output;
+use crate::snforge;
+
+/// Value of the `format` field identifying the file.
+const FORMAT: &str = "burnprofile";
+
+/// Version written by this release, older versions are read as well.
+pub const VERSION: u32 = 1;
+
+/// Profile saved with the program and the arguments it was produced by, so that it can be
+/// rendered by later releases and told apart from the profiles of other runs.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BurnProfile {
+    /// Always `burnprofile`.
+    pub format: String,
+    pub version: u32,
+    /// Hash of the Sierra program, `None` if the profile wasn't produced by running one.
+    pub program_hash: Option<String>,
+    /// Hash of the arguments of the runs, `None` if there were none or they are unknown.
+    pub arguments_hash: Option<String>,
+    /// Weight model of the values, e.g. `steps`, `None` if unknown.
+    pub weight: Option<String>,
+    /// Tree of the call stacks under an `all` root.
+    pub root: Node,
+}
+
+/// Frame of the weight tree with the total weight of its subtree.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub value: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    /// Tree of the stacks of a folded profile under an `all` root.
+    pub fn from_folded(folded: &str) -> Self {
+        let mut root = Node {
+            name: "all".into(),
+            ..Default::default()
+        };
+        for (stack, weight) in folded::lines(folded) {
+            let mut node = &mut root;
+            node.value += weight;
+            for frame in stack.split(';') {
+                let idx = match node.children.iter().position(|child| child.name == frame) {
+                    Some(idx) => idx,
+                    None => {
+                        node.children.push(Node {
+                            name: frame.into(),
+                            ..Default::default()
+                        });
+                        node.children.len() - 1
+                    }
+                };
+                node = &mut node.children[idx];
+                node.value += weight;
+            }
+        }
+        root
+    }
+
+    /// Folded stacks of the tree below the root, a stack per node with a self weight.
+    pub fn folded(&self) -> String {
+        fn visit(node: &Node, stack: &mut Vec<String>, output: &mut String) {
+            stack.push(node.name.clone());
+            let children: usize = node.children.iter().map(|child| child.value).sum();
+            if node.value > children {
+                output.push_str(&format!("{} {}\n", stack.join(";"), node.value - children));
+            }
+            for child in &node.children {
+                visit(child, stack, output);
+            }
+            stack.pop();
+        }
+        let mut output = String::new();
+        for child in &self.children {
+            visit(child, &mut Vec::new(), &mut output);
+        }
+        output
+    }
+}
+
+impl BurnProfile {
+    pub fn new(
+        folded: &str,
+        program_hash: Option<String>,
+        arguments_hash: Option<String>,
+        weight: Option<String>,
+    ) -> Self {
+        Self {
+            format: FORMAT.into(),
+            version: VERSION,
+            program_hash,
+            arguments_hash,
+            weight,
+            root: Node::from_folded(folded),
+        }
+    }
+}
+
+/// Hash identifying the contents, the 64-bit FNV-1a which doesn't change between releases.
+pub fn hash(contents: &str) -> String {
+    let hash = contents.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// Folded stacks of a saved profile, a burnprofile, a folded profile or an snforge trace.
+pub fn load(path: &Utf8PathBuf) -> Result<String> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read profile file at {path}"))?;
+    let (folded, _) = contents_folded(&contents).with_context(|| format!("in {path}"))?;
+    Ok(folded)
+}
+
+/// Folded stacks and the weight model of the contents of a saved profile, the weight known
+/// only of burnprofiles.
+pub fn contents_folded(contents: &str) -> Result<(String, Option<String>)> {
+    if !contents.trim_start().starts_with('{') {
+        return Ok((contents.to_string(), None));
+    }
+    if snforge::is_trace(contents) {
+        return Ok((snforge::folded(contents).context("invalid snforge trace")?, None));
+    }
+    let profile = parse(contents).context("invalid burnprofile")?;
+    Ok((profile.root.folded(), profile.weight))
+}
+
+/// Parse a burnprofile of a version this release reads.
+pub fn parse(contents: &str) -> Result<BurnProfile> {
+    let profile: BurnProfile = serde_json::from_str(contents)?;
+    ensure!(profile.format == FORMAT, "not a burnprofile");
+    ensure!(
+        profile.version <= VERSION,
+        "version {} is newer than {VERSION} read by this release",
+        profile.version
+    );
+    Ok(profile)
+}
+
+pub fn write(profile: &BurnProfile, path: &Utf8PathBuf) -> Result<()> {
+    output::write(path, serde_json::to_string_pretty(profile)? + "\n")
+        .with_context(|| format!("failed to write burnprofile: {path}"))
+}
+
+/// Whether the path has the burnprofile extension.
+pub fn is_burnprofile(path: &Utf8PathBuf) -> bool {
+    path.extension() == Some(FORMAT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burnprofile() {
+        let folded = "main;foo;store_temp 10\nmain;foo 2\nmain;bar;jump 5\n";
+        let profile = BurnProfile::new(folded, None, None, Some("steps".into()));
+        assert_eq!(profile.root.value, 17);
+        assert_eq!(profile.root.children[0].children[0].value, 12);
+        assert_eq!(profile.root.folded(), "main;foo 2\nmain;foo;store_temp 10\nmain;bar;jump 5\n");
+
+        let json = serde_json::to_string(&profile).unwrap();
+        let parsed: BurnProfile = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.root, profile.root);
+        assert_eq!(hash(""), "cbf29ce484222325");
+        assert_eq!(hash("a"), "af63dc4c8601ec8c");
+    }
+}
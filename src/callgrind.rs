@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+use crate::folded;
+use crate::output;
+
+/// Convert a folded profile to the callgrind format, frames become functions without
+/// source positions. Calls are counted from the `function_call` leaves, a call executing
+/// a single step, which is exact for steps-weighted scoped stacks; otherwise a caller-callee
+/// pair counts as a single call.
+pub fn callgrind(folded: &str) -> String {
+    let mut self_costs: BTreeMap<&str, usize> = BTreeMap::new();
+    // Inclusive cost and calls per caller and callee
+    let mut edges: BTreeMap<&str, BTreeMap<&str, (usize, usize)>> = BTreeMap::new();
+    for (stack, weight) in folded::lines(folded) {
+        let frames: Vec<&str> = stack.split(';').collect();
+        let Some((leaf, callers)) = frames.split_last() else {
+            continue;
+        };
+        *self_costs.entry(*leaf).or_default() += weight;
+        for frame in callers {
+            self_costs.entry(*frame).or_default();
+        }
+        // Recursive edges are counted once per stack
+        let mut seen = HashSet::new();
+        for pair in frames.windows(2).filter(|pair| seen.insert((pair[0], pair[1]))) {
+            edges
+                .entry(pair[0])
+                .or_default()
+                .entry(pair[1])
+                .or_default()
+                .0 += weight;
+        }
+    }
+    let call_counts = folded::calls(folded);
+    for (caller, callees) in edges.iter_mut() {
+        for (callee, (_, calls)) in callees.iter_mut() {
+            *calls = call_counts.get(&(*caller, *callee)).copied().unwrap_or(1);
+        }
+    }
+
+    let mut output = String::new();
+    output.push_str("# callgrind format\nversion: 1\n");
+    output.push_str(concat!("creator: scarb-burn ", env!("CARGO_PKG_VERSION"), "\n"));
+    output.push_str("positions: line\nevents: Weight\n");
+    for (function, self_cost) in &self_costs {
+        let _ = write!(output, "\nfn={function}\n0 {self_cost}\n");
+        for (callee, (cost, calls)) in edges.get(function).into_iter().flatten() {
+            let _ = write!(output, "cfn={callee}\ncalls={calls} 0\n0 {cost}\n");
+        }
+    }
+    output
+}
+
+/// Write a folded profile as a callgrind file.
+pub fn write(folded: &str, path: &Utf8PathBuf) -> Result<()> {
+    output::write(path, callgrind(folded))
+        .with_context(|| format!("failed to write callgrind file: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callgrind() {
+        let output =
+            callgrind("main;foo;store_temp 10\nmain;function_call<user@foo> 3\nmain;return 1");
+        assert!(output.contains("\nfn=main\n0 0\ncfn=foo\ncalls=3 0\n0 10\n"));
+        assert!(output.contains("cfn=return\ncalls=1 0\n0 1\n"));
+        assert!(output.contains("\nfn=store_temp\n0 10\n"));
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use serde_json::{json, Value};
+
+use crate::folded;
+use crate::output;
+
+/// Convert a folded profile to Chrome trace events on a synthetic timeline. Stacks are laid out
+/// one after another in the order of the profile, which follows the first execution of each
+/// scoped statement, a weight unit lasting a microsecond. Frames shared by consecutive stacks
+/// are merged into a single event.
+pub fn trace(folded: &str) -> Value {
+    let mut events = Vec::new();
+    // Open frames with their start time
+    let mut open: Vec<(&str, usize)> = Vec::new();
+    let mut now = 0;
+    let mut close = |open: &mut Vec<(&str, usize)>, depth: usize, now: usize| {
+        while open.len() > depth {
+            let (name, start) = open.pop().unwrap();
+            events.push(json!({
+                "name": name,
+                "ph": "X",
+                "ts": start,
+                "dur": now - start,
+                "pid": 1,
+                "tid": 1,
+            }));
+        }
+    };
+    for (stack, weight) in folded::lines(folded) {
+        let frames: Vec<&str> = stack.split(';').collect();
+        let common = open
+            .iter()
+            .zip(&frames)
+            .take_while(|((name, _), frame)| name == *frame)
+            .count();
+        close(&mut open, common, now);
+        open.extend(frames[common..].iter().map(|frame| (*frame, now)));
+        now += weight;
+    }
+    close(&mut open, 0, now);
+    json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ns",
+        "otherData": { "exporter": concat!("scarb-burn ", env!("CARGO_PKG_VERSION")) },
+    })
+}
+
+/// Write a folded profile as a Chrome trace event JSON file.
+pub fn write(folded: &str, path: &Utf8PathBuf) -> Result<()> {
+    output::write(path, serde_json::to_string(&trace(folded))?)
+        .with_context(|| format!("failed to write trace: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace() {
+        let trace = trace("main;foo 10\nmain;foo;bar 5\nmain;baz 1");
+        let events: Vec<_> = trace["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| {
+                (
+                    event["name"].as_str().unwrap(),
+                    event["ts"].as_u64().unwrap(),
+                    event["dur"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            events,
+            vec![("bar", 10, 5), ("foo", 0, 15), ("baz", 15, 1), ("main", 0, 16)]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use cairo_lang_runner::Arg;
+use cairo_lang_sierra::program::Program;
+use cairo_lang_starknet_classes::contract_class::ContractClass;
+use cairo_lang_starknet_classes::keccak::starknet_keccak;
+use camino::Utf8PathBuf;
+use num_bigint::BigUint;
+use serde_json::Value;
+use std::fs;
+
+use crate::profiler::{self, Profile, ProfileOptions};
+use crate::rpc::Call;
+
+/// Load a Starknet contract class artifact.
+pub fn load_contract_class(path: &Utf8PathBuf) -> Result<ContractClass> {
+    serde_json::from_str::<ContractClass>(
+        &fs::read_to_string(path)
+            .with_context(|| format!("failed to read contract class: {path}"))?,
+    )
+    .with_context(|| format!("failed to deserialize contract class: {path}"))
+}
+
+/// Extract the Sierra program embedded into the contract class, along with debug names if present.
+pub fn extract_program(contract_class: &ContractClass) -> Result<Program> {
+    contract_class
+        .extract_sierra_program()
+        .with_context(|| "failed to extract Sierra program from contract class")
+}
+
+/// Run an external function of the contract and generate a profile.
+/// The entrypoint is either a function name or a hex selector.
+pub fn profile(
+    contract_class: &ContractClass,
+    entrypoint: &str,
+    calldata: Vec<Arg>,
+    options: &ProfileOptions,
+) -> Result<Profile> {
+    let selector = parse_selector(entrypoint);
+    let entry_point = contract_class
+        .entry_points_by_type
+        .external
+        .iter()
+        .find(|entry_point| entry_point.selector == selector)
+        .with_context(|| format!("external function `{entrypoint}` not found"))?;
+
+    let sierra_program = extract_program(contract_class)?;
+    let function = sierra_program
+        .funcs
+        .get(entry_point.function_idx)
+        .with_context(|| format!("function #{} not found", entry_point.function_idx))?
+        .clone();
+
+    // External function wrappers deserialize the calldata and dispatch the call
+    profiler::profile_function(
+        sierra_program,
+        &function,
+        vec![Arg::Array(calldata)],
+        options,
+    )
+}
+
+/// Calldata of the first call to the entrypoint, e.g. among the calls of a transaction.
+pub fn entrypoint_calldata(calls: Vec<Call>, entrypoint: &str) -> Result<Vec<Arg>> {
+    let selector = parse_selector(entrypoint);
+    let call = calls
+        .into_iter()
+        .find(|call| call.selector == selector)
+        .with_context(|| format!("transaction does not call `{entrypoint}`"))?;
+    Ok(call
+        .calldata
+        .into_iter()
+        .map(|felt| Arg::Value(felt.into()))
+        .collect())
+}
+
+/// Parameter names of an external function according to the contract ABI.
+pub fn param_names(contract_class: &ContractClass, entrypoint: &str) -> Result<Vec<String>> {
+    let abi = serde_json::to_value(
+        contract_class
+            .abi
+            .as_ref()
+            .with_context(|| "contract class has no ABI")?,
+    )?;
+    let selector = parse_selector(entrypoint);
+    let function = abi_functions(&abi)
+        .into_iter()
+        .find(|function| {
+            function["name"]
+                .as_str()
+                .is_some_and(|name| starknet_keccak(name.as_bytes()) == selector)
+        })
+        .with_context(|| format!("function `{entrypoint}` not found in the ABI"))?;
+    function["inputs"]
+        .as_array()
+        .with_context(|| format!("function `{entrypoint}` has no inputs in the ABI"))?
+        .iter()
+        .map(|input| {
+            input["name"]
+                .as_str()
+                .map(str::to_string)
+                .with_context(|| "ABI input has no name")
+        })
+        .collect()
+}
+
+/// Functions of the ABI, including the ones nested into interfaces.
+fn abi_functions(items: &Value) -> Vec<&Value> {
+    let mut functions = Vec::new();
+    for item in items.as_array().into_iter().flatten() {
+        match item["type"].as_str() {
+            Some("function") => functions.push(item),
+            Some("interface") => functions.extend(abi_functions(&item["items"])),
+            _ => {}
+        }
+    }
+    functions
+}
+
+fn parse_selector(entrypoint: &str) -> BigUint {
+    entrypoint
+        .strip_prefix("0x")
+        .and_then(|hex| BigUint::parse_bytes(hex.as_bytes(), 16))
+        .unwrap_or_else(|| starknet_keccak(entrypoint.as_bytes()))
+}
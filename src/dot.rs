@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+use crate::folded;
+use crate::output;
+use crate::report::FunctionReport;
+
+/// Render the call graph of a folded profile in the Graphviz DOT language, libfunc leaves are
+/// merged into their callers. Node sizes and colors follow the inclusive weights of the
+/// functions, edges are labeled with the weights of the calls.
+pub fn call_graph(folded: &str) -> String {
+    let functions = FunctionReport::new(folded);
+    let mut edges: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for (stack, weight) in folded::lines(folded) {
+        let frames: Vec<&str> = stack.split(';').collect();
+        let Some((_, callers)) = frames.split_last() else {
+            continue;
+        };
+        // Recursive edges are counted once per stack
+        let mut seen = HashSet::new();
+        for pair in callers.windows(2).filter(|pair| seen.insert((pair[0], pair[1]))) {
+            *edges.entry((pair[0], pair[1])).or_default() += weight;
+        }
+    }
+
+    let total = functions.total.max(1) as f64;
+    let mut output = String::from("digraph profile {\n");
+    output.push_str("  node [shape=box, style=filled, fontname=\"monospace\"];\n");
+    for (name, weights) in &functions.functions {
+        let share = weights.inclusive as f64 / total;
+        let _ = writeln!(
+            output,
+            "  {} [label=\"{}\\n{} ({:.2}%)\\nself {}\", fontsize={:.1}, fillcolor=\"{:.3} {:.3} 1.000\"];",
+            quote(name),
+            escape(name),
+            weights.inclusive,
+            100.0 * share,
+            weights.exclusive,
+            10.0 + 14.0 * share,
+            // From pale yellow for light functions to saturated red for the heaviest ones
+            0.15 * (1.0 - share),
+            0.2 + 0.8 * share
+        );
+    }
+    for ((caller, callee), weight) in &edges {
+        let share = *weight as f64 / total;
+        let _ = writeln!(
+            output,
+            "  {} -> {} [label=\"{weight}\", penwidth={:.2}];",
+            quote(caller),
+            quote(callee),
+            1.0 + 4.0 * share
+        );
+    }
+    output.push_str("}\n");
+    output
+}
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", escape(name))
+}
+
+/// Write the call graph of a folded profile as a DOT file.
+pub fn write(folded: &str, path: &Utf8PathBuf) -> Result<()> {
+    output::write(path, call_graph(folded))
+        .with_context(|| format!("failed to write call graph: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_graph() {
+        let graph = call_graph("main;foo;store_temp 30\nmain;foo;bar;jump 10\nmain;return 10");
+        assert!(graph.contains("\"main\" -> \"foo\" [label=\"40\", penwidth=4.20];"));
+        assert!(graph.contains("\"foo\" -> \"bar\" [label=\"10\", penwidth=1.80];"));
+        assert!(graph.contains("\"foo\" [label=\"foo\\n40 (80.00%)\\nself 30\""));
+        assert!(!graph.contains("store_temp"));
+    }
+}
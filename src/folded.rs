@@ -0,0 +1,236 @@
+use cairo_lang_utils::ordered_hash_map::OrderedHashMap;
+use std::collections::BTreeMap;
+
+/// Iterate over `stack weight` lines of a folded profile, skipping malformed ones.
+pub fn lines(folded: &str) -> impl Iterator<Item = (&str, usize)> {
+    folded.lines().filter_map(|line| {
+        let (stack, weight) = line.rsplit_once(' ')?;
+        Some((stack, weight.parse().ok()?))
+    })
+}
+
+/// Sum of sample counts of the folded stacks.
+pub fn total_weight(folded: &str) -> usize {
+    lines(folded).map(|(_, weight)| weight).sum()
+}
+
+/// Sum weights of identical stacks across several profiles,
+/// stacks are kept in the order of their first appearance.
+pub fn merge<'a>(profiles: impl IntoIterator<Item = &'a str>) -> String {
+    let mut weights: OrderedHashMap<&str, usize> = OrderedHashMap::default();
+    for (stack, weight) in profiles.into_iter().flat_map(lines) {
+        *weights.entry(stack).or_default() += weight;
+    }
+    weights
+        .iter()
+        .map(|(stack, weight)| format!("{stack} {weight}\n"))
+        .collect()
+}
+
+/// Calls per caller and callee counted from the `function_call<user@callee>` leaves, which is
+/// exact for steps-weighted scoped stacks as a call executes a single step.
+pub fn calls(folded: &str) -> BTreeMap<(&str, &str), usize> {
+    let mut calls = BTreeMap::new();
+    for (stack, weight) in lines(folded) {
+        let Some((callers, leaf)) = stack.rsplit_once(';') else {
+            continue;
+        };
+        let caller = callers.rsplit(';').next().unwrap_or(callers);
+        if let Some(callee) = leaf
+            .strip_prefix("function_call<user@")
+            .and_then(|name| name.strip_suffix('>'))
+        {
+            *calls.entry((caller, callee)).or_default() += weight;
+        }
+    }
+    calls
+}
+
+/// Calls per function summed over its callers, see [`calls`].
+pub fn calls_per_function(folded: &str) -> BTreeMap<&str, usize> {
+    let mut calls: BTreeMap<&str, usize> = BTreeMap::new();
+    for ((_, callee), count) in self::calls(folded) {
+        *calls.entry(callee).or_default() += count;
+    }
+    calls
+}
+
+/// Move the weight of dict squashing, paid when a dict is destructed, to the stacks accessing
+/// dicts proportionally to their weight, as `<caller>;felt252_dict_squash` frames.
+pub fn attribute_dict_squash(folded: &str) -> String {
+    let is_squash = |stack: &str| stack.split(';').any(|frame| frame.contains("squash"));
+    let is_access = |stack: &str| {
+        !is_squash(stack)
+            && stack
+                .rsplit(';')
+                .next()
+                .is_some_and(|leaf| leaf.starts_with("felt252_dict_entry_get"))
+    };
+    let squash_weight: usize = lines(folded)
+        .filter(|(stack, _)| is_squash(stack))
+        .map(|(_, weight)| weight)
+        .sum();
+    let accesses: Vec<_> = lines(folded)
+        .filter(|(stack, _)| is_access(stack))
+        .collect();
+    let access_weight: usize = accesses.iter().map(|(_, weight)| weight).sum();
+    if squash_weight == 0 || access_weight == 0 {
+        return folded.to_string();
+    }
+
+    let mut result: Vec<String> = lines(folded)
+        .filter(|(stack, _)| !is_squash(stack))
+        .map(|(stack, weight)| format!("{stack} {weight}"))
+        .collect();
+    let mut remaining = squash_weight;
+    for (idx, (stack, weight)) in accesses.iter().enumerate() {
+        // The last access takes the rounding remainder
+        let share = if idx + 1 == accesses.len() {
+            remaining
+        } else {
+            squash_weight * weight / access_weight
+        };
+        remaining -= share;
+        let caller = stack.rsplit_once(';').map_or(*stack, |(caller, _)| caller);
+        result.push(format!("{caller};felt252_dict_squash {share}"));
+    }
+    merge([result.join("\n").as_str()])
+}
+
+/// Strip the generic arguments of a frame, e.g. `core::array::ArrayImpl::<felt252>::append`
+/// becomes `core::array::ArrayImpl::append` and `store_temp<felt252>` becomes `store_temp`.
+pub fn strip_generics(frame: &str) -> String {
+    let mut stripped = String::new();
+    let mut depth = 0usize;
+    for c in frame.chars() {
+        match c {
+            '<' => {
+                if depth == 0 && stripped.ends_with("::") {
+                    stripped.truncate(stripped.len() - 2);
+                }
+                depth += 1;
+            }
+            '>' if depth > 0 => depth -= 1,
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// Name of a frame which is stable across builds of changed code, used to match the frames
+/// of two profiles. The generic arguments are stripped, as their instantiations differ between
+/// builds, and so are the expression ids of loop functions, e.g. `app::sum[expr12]` becomes
+/// `app::sum[expr]`.
+pub fn logical_name(frame: &str) -> String {
+    let stripped = strip_generics(frame);
+    let mut name = String::new();
+    let mut rest = stripped.as_str();
+    while let Some(start) = rest.find("[expr") {
+        let (before, after) = rest.split_at(start + "[expr".len());
+        name.push_str(before);
+        let digits = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        rest = &after[digits..];
+    }
+    name.push_str(rest);
+    name
+}
+
+/// Rename the frames of every stack, merging the stacks which become identical.
+pub fn rename_frames(folded: &str, frame: impl Fn(&str) -> String) -> String {
+    let stacks: Vec<String> = lines(folded)
+        .map(|(stack, weight)| {
+            let frames: Vec<String> = stack.split(';').map(&frame).collect();
+            format!("{} {weight}", frames.join(";"))
+        })
+        .collect();
+    merge([stacks.join("\n").as_str()])
+}
+
+/// Map the frames of every stack, e.g. to their modules, merging consecutive identical frames.
+/// With `drop_leaf` the leaf frames are dropped and their weight goes to the callers.
+pub fn collapse(folded: &str, drop_leaf: bool, frame: impl Fn(&str) -> String) -> String {
+    let stacks: Vec<String> = lines(folded)
+        .map(|(stack, weight)| {
+            let mut frames: Vec<&str> = stack.split(';').collect();
+            if drop_leaf && frames.len() > 1 {
+                frames.pop();
+            }
+            let mut collapsed: Vec<String> = Vec::new();
+            for mapped in frames.into_iter().map(&frame) {
+                if collapsed.last() != Some(&mapped) {
+                    collapsed.push(mapped);
+                }
+            }
+            format!("{} {weight}", collapsed.join(";"))
+        })
+        .collect();
+    merge([stacks.join("\n").as_str()])
+}
+
+/// Collapse recursive cycles of every stack into their first occurrence, e.g. `a;b;a;b;c`
+/// becomes `a;b;c`, the way scoped stacks collapse loops.
+pub fn collapse_recursion(folded: &str) -> String {
+    let stacks: Vec<String> = lines(folded)
+        .map(|(stack, weight)| {
+            let mut frames: Vec<&str> = Vec::new();
+            for frame in stack.split(';') {
+                match frames.iter().position(|seen| *seen == frame) {
+                    Some(idx) => frames.truncate(idx + 1),
+                    None => frames.push(frame),
+                }
+            }
+            format!("{} {weight}", frames.join(";"))
+        })
+        .collect();
+    merge([stacks.join("\n").as_str()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let merged = merge(["main;foo 10\nmain;bar 5\n", "main;baz 1\nmain;foo 2\n"]);
+        assert_eq!(merged, "main;foo 12\nmain;bar 5\nmain;baz 1\n");
+        assert_eq!(total_weight(&merged), 18);
+    }
+
+    #[test]
+    fn test_attribute_dict_squash() {
+        let folded = "main;a;felt252_dict_entry_get 30\n\
+            main;b;felt252_dict_entry_get 10\n\
+            main;b;store_temp 5\n\
+            main;core::dict::Felt252DictDestruct::destruct;felt252_dict_squash 100";
+        assert_eq!(
+            attribute_dict_squash(folded),
+            "main;a;felt252_dict_entry_get 30\n\
+            main;b;felt252_dict_entry_get 10\n\
+            main;b;store_temp 5\n\
+            main;a;felt252_dict_squash 75\n\
+            main;b;felt252_dict_squash 25\n"
+        );
+        assert_eq!(attribute_dict_squash("main;foo 1"), "main;foo 1");
+    }
+
+    #[test]
+    fn test_collapse_recursion() {
+        assert_eq!(
+            collapse_recursion("main;a;b;a;b;c 2\nmain;a;a;a 3\nmain;a 1"),
+            "main;a;b;c 2\nmain;a 4\n"
+        );
+    }
+
+    #[test]
+    fn test_collapse() {
+        let folded = "app::main;app::a::f;app::a::g;store_temp 3\n\
+            app::main;core::array::append;array_append 2\n\
+            app::main;app::a::f;jump 1";
+        let module = |frame: &str| frame.rsplit_once("::").map_or(frame, |(m, _)| m).to_string();
+        assert_eq!(
+            collapse(folded, true, module),
+            "app;app::a 4\napp;core::array 2\n"
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{bail, Context, Result};
+use cairo_lang_sierra::program::Program;
+use num_bigint::{BigInt, BigUint};
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_json::Value;
+
+use crate::arguments::{self, ArgType};
+use crate::folded;
+use crate::outln;
+use crate::profiler::{self, ProfileOptions};
+
+/// Maximum length of generated arrays and byte arrays.
+const MAX_LEN: usize = 16;
+
+/// Profile the function with `iterations` random inputs generated from its signature,
+/// print the most expensive one and return the aggregated profile.
+pub fn fuzz(
+    program: &Program,
+    function: &str,
+    iterations: usize,
+    seed: u64,
+    options: &ProfileOptions,
+) -> Result<String> {
+    let param_types = arguments::param_types(program, function)?;
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut results = Vec::new();
+    let mut worst: Option<(usize, usize, Vec<Value>)> = None;
+    let mut failed = 0;
+    for iteration in 0..iterations {
+        let values: Vec<Value> = param_types
+            .iter()
+            .map(|ty| random_value(ty, &mut rng))
+            .collect();
+        let program_args = arguments::typed_args(program, function, values.clone())?;
+        let profile = profiler::profile(program.clone(), function, program_args, options)
+            .and_then(|profile| match &profile.panic_message {
+                Some(msg) => bail!("panicked with [{msg}]"),
+                None => Ok(profile),
+            });
+        match profile {
+            Ok(profile) => {
+                let result = profiler::folded(&profile, options);
+                let weight = folded::total_weight(&result);
+                if worst.as_ref().is_none_or(|(_, max, _)| weight > *max) {
+                    worst = Some((iteration, weight, values));
+                }
+                results.push(result);
+            }
+            Err(err) => {
+                eprintln!("Input #{iteration} failed: {err:#}");
+                failed += 1;
+            }
+        }
+    }
+
+    outln!("Fuzzed `{function}` with {iterations} inputs (seed {seed}), {failed} failed");
+    let (iteration, weight, values) = worst.context("no input was profiled successfully")?;
+    outln!(
+        "Worst case input #{iteration} with total weight {weight}:\n{}",
+        Value::Array(values)
+    );
+    Ok(folded::merge(results.iter().map(String::as_str)))
+}
+
+/// Generate a random value of the given type, in the `--typed` JSON format.
+fn random_value(ty: &ArgType, rng: &mut StdRng) -> Value {
+    match ty {
+        ArgType::Felt => Value::String(random_int(rng, 251, false).to_string()),
+        ArgType::Integer { bits, signed } => {
+            Value::String(random_int(rng, *bits, *signed).to_string())
+        }
+        ArgType::Bool => Value::Bool(rng.gen()),
+        ArgType::U256 => Value::String(random_int(rng, 256, false).to_string()),
+        ArgType::ByteArray => {
+            let len = rng.gen_range(0..=MAX_LEN * 4);
+            Value::String(
+                (0..len)
+                    .map(|_| char::from(rng.sample(Alphanumeric)))
+                    .collect(),
+            )
+        }
+        ArgType::Array(ty) => {
+            let len = rng.gen_range(0..=MAX_LEN);
+            Value::Array((0..len).map(|_| random_value(ty, rng)).collect())
+        }
+        ArgType::Struct(types) => {
+            Value::Array(types.iter().map(|ty| random_value(ty, rng)).collect())
+        }
+    }
+}
+
+/// Random integer of the given width, biased towards the bounds and small values
+/// since those are the usual edge cases.
+fn random_int(rng: &mut StdRng, bits: u32, signed: bool) -> BigInt {
+    let (min, max) = if signed {
+        let half = BigInt::from(1) << (bits - 1);
+        (-half.clone(), half - 1)
+    } else {
+        (BigInt::from(0), (BigInt::from(1) << bits) - 1)
+    };
+    match rng.gen_range(0..5) {
+        0 => BigInt::from(0),
+        1 => min,
+        2 => max,
+        3 => BigInt::from(rng.gen_range(0..=u8::MAX)).min(max),
+        _ => {
+            let mut bytes = vec![0u8; bits.div_ceil(8) as usize];
+            rng.fill(&mut bytes[..]);
+            let mask = (BigUint::from(1u8) << bits) - 1u8;
+            BigInt::from(BigUint::from_bytes_be(&bytes) & mask) + min
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_int() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let value = random_int(&mut rng, 8, true);
+            assert!((-128..=127).contains(&i64::try_from(value).unwrap()));
+            let value = random_int(&mut rng, 8, false);
+            assert!((0..=255).contains(&i64::try_from(value).unwrap()));
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::folded;
+use crate::output;
+
+/// Version of the Gecko profile format, newer ones are upgraded by the Firefox Profiler.
+const GECKO_VERSION: u32 = 24;
+
+/// Convert a folded profile to the Gecko profile format of the Firefox Profiler, a sample
+/// per stack weighted by its weight. Sample times advance by the weights, a weight unit
+/// lasting a millisecond, so that the time-based views keep the proportions.
+pub fn profile(folded: &str, name: &str) -> Value {
+    let mut strings: Vec<&str> = Vec::new();
+    let mut frames: Vec<Value> = Vec::new();
+    let mut frame_indices: HashMap<&str, usize> = HashMap::new();
+    let mut stacks: Vec<Value> = Vec::new();
+    let mut stack_indices: HashMap<(Option<usize>, usize), usize> = HashMap::new();
+    let mut samples = Vec::new();
+    let mut time = 0;
+    for (stack, weight) in folded::lines(folded) {
+        let mut prefix = None;
+        for frame in stack.split(';') {
+            let frame_idx = *frame_indices.entry(frame).or_insert_with(|| {
+                strings.push(frame);
+                // location, relevantForJS, innerWindowID, implementation, line, column,
+                // category, subcategory
+                frames.push(json!([strings.len() - 1, false, 0, null, null, null, 0, 0]));
+                frames.len() - 1
+            });
+            let stack_idx = *stack_indices
+                .entry((prefix, frame_idx))
+                .or_insert_with(|| {
+                    stacks.push(json!([prefix, frame_idx]));
+                    stacks.len() - 1
+                });
+            prefix = Some(stack_idx);
+        }
+        samples.push(json!([prefix, time, weight]));
+        time += weight;
+    }
+    json!({
+        "meta": {
+            "version": GECKO_VERSION,
+            "startTime": 0,
+            "shutdownTime": null,
+            "interval": 1,
+            "stackwalk": 0,
+            "debug": 0,
+            "gcpoison": 0,
+            "asyncstack": 0,
+            "processType": 0,
+            "product": name,
+            "categories": [{ "name": "Other", "color": "grey", "subcategories": ["Other"] }],
+            "markerSchema": [],
+        },
+        "libs": [],
+        "pausedRanges": [],
+        "processes": [],
+        "threads": [{
+            "name": "main",
+            "processType": "default",
+            "processName": "scarb-burn",
+            "tid": 0,
+            "pid": 0,
+            "registerTime": 0,
+            "unregisterTime": null,
+            "markers": {
+                "schema": {
+                    "name": 0,
+                    "startTime": 1,
+                    "endTime": 2,
+                    "phase": 3,
+                    "category": 4,
+                    "data": 5,
+                },
+                "data": [],
+            },
+            "samples": {
+                "schema": { "stack": 0, "time": 1, "weight": 2 },
+                "weightType": "samples",
+                "data": samples,
+            },
+            "frameTable": {
+                "schema": {
+                    "location": 0,
+                    "relevantForJS": 1,
+                    "innerWindowID": 2,
+                    "implementation": 3,
+                    "line": 4,
+                    "column": 5,
+                    "category": 6,
+                    "subcategory": 7,
+                },
+                "data": frames,
+            },
+            "stackTable": {
+                "schema": { "prefix": 0, "frame": 1 },
+                "data": stacks,
+            },
+            "stringTable": strings,
+        }],
+    })
+}
+
+/// Write a folded profile as a Gecko profile JSON file.
+pub fn write(folded: &str, path: &Utf8PathBuf) -> Result<()> {
+    let name = path.file_stem().unwrap_or("scarb-burn");
+    output::write(path, serde_json::to_string(&profile(folded, name))?)
+        .with_context(|| format!("failed to write Firefox profile: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let profile = profile("main;foo 10\nmain;bar 5\nmain;foo 1", "test");
+        let thread = &profile["threads"][0];
+        assert_eq!(thread["stringTable"], json!(["main", "foo", "bar"]));
+        assert_eq!(
+            thread["stackTable"]["data"],
+            json!([[null, 0], [0, 1], [0, 2]])
+        );
+        assert_eq!(
+            thread["samples"]["data"],
+            json!([[1, 0, 10], [2, 10, 5], [1, 15, 1]])
+        );
+    }
+}
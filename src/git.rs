@@ -0,0 +1,59 @@
+use anyhow::{ensure, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::process::Command;
+
+/// Checkout of another ref of the repository of the current directory, removed when dropped.
+pub struct Worktree {
+    /// Root of the repository.
+    root: Utf8PathBuf,
+    pub path: Utf8PathBuf,
+}
+
+impl Worktree {
+    /// Check out the ref with a detached HEAD at the path, which must not exist.
+    pub fn add(git_ref: &str, path: Utf8PathBuf) -> Result<Self> {
+        let root = git(&["rev-parse", "--show-toplevel"])?.into();
+        git(&["worktree", "add", "--detach", "--quiet", path.as_str(), git_ref])
+            .with_context(|| format!("failed to check out `{git_ref}`"))?;
+        Ok(Self { root, path })
+    }
+
+    /// Run git in the worktree and return its trimmed standard output.
+    pub fn git(&self, args: &[&str]) -> Result<String> {
+        git(&[&["-C", self.path.as_str()][..], args].concat())
+    }
+
+    /// Path in the worktree corresponding to a path in the repository.
+    pub fn map(&self, path: &Utf8Path) -> Option<Utf8PathBuf> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        Some(self.path.join(relative))
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        if let Err(err) = git(&["worktree", "remove", "--force", self.path.as_str()]) {
+            eprintln!("Failed to remove the worktree at {}: {err:#}", self.path);
+        }
+    }
+}
+
+/// Commit checked out in the current directory, `None` outside of a git repository.
+pub fn head() -> Option<String> {
+    git(&["rev-parse", "HEAD"]).ok()
+}
+
+/// Run git and return its trimmed standard output.
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .with_context(|| "failed to run git")?;
+    ensure!(
+        output.status.success(),
+        "`git {}` failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
@@ -0,0 +1,216 @@
+use anyhow::{ensure, Context, Result};
+use camino::Utf8PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::git;
+use crate::output;
+
+/// Directory of the run histories, a JSON Lines file per profile.
+const DIR: &str = ".burn/history";
+
+/// Totals of a recorded run.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Record {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Commit checked out during the run, `None` outside of a git repository.
+    pub commit: Option<String>,
+    /// Total weights by the name of the weight model, e.g. `steps` or `sierra_gas`.
+    pub weights: BTreeMap<String, usize>,
+    /// Builtin instances used by the run.
+    pub builtins: BTreeMap<String, usize>,
+}
+
+impl Record {
+    /// Record of a run at the current time and commit.
+    pub fn new(weights: BTreeMap<String, usize>, builtins: BTreeMap<String, usize>) -> Self {
+        Self {
+            timestamp: now(),
+            commit: git::head(),
+            weights,
+            builtins,
+        }
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// History file of the given profile, e.g. of a package or an entrypoint.
+pub fn path(name: &str) -> Utf8PathBuf {
+    Utf8PathBuf::from(DIR).join(format!("{}.jsonl", output::file_stem(name)))
+}
+
+/// Append the record to the history of the given profile.
+pub fn append(name: &str, record: &Record) -> Result<Utf8PathBuf> {
+    let path = path(name);
+    fs::create_dir_all(DIR).with_context(|| format!("failed to create {DIR}"))?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("failed to open history: {path}"))?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+        .with_context(|| format!("failed to write history: {path}"))?;
+    Ok(path)
+}
+
+/// Histories by the profile name, of the given profile or of all recorded ones.
+pub fn load(name: Option<&str>) -> Result<BTreeMap<String, Vec<Record>>> {
+    let paths = match name {
+        Some(name) => {
+            let path = path(name);
+            ensure!(
+                path.exists(),
+                "no history of `{name}` at {path}, record runs with `--record-history`"
+            );
+            vec![path]
+        }
+        None => match fs::read_dir(DIR) {
+            Ok(entries) => entries
+                .filter_map(|entry| Utf8PathBuf::try_from(entry.ok()?.path()).ok())
+                .filter(|path| path.extension() == Some("jsonl"))
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+    };
+
+    let mut histories = BTreeMap::new();
+    for path in paths {
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read history: {path}"))?;
+        let records = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("invalid record at {path}:{}", idx + 1))
+            })
+            .collect::<Result<Vec<Record>>>()?;
+        let name = path.file_stem().unwrap_or_default().to_string();
+        histories.insert(name, records);
+    }
+    Ok(histories)
+}
+
+/// Weight models recorded in any of the records.
+fn columns<'a>(records: impl IntoIterator<Item = &'a Record>) -> BTreeSet<&'a str> {
+    records
+        .into_iter()
+        .flat_map(|record| record.weights.keys().map(String::as_str))
+        .collect()
+}
+
+/// Render the histories as a table per profile, a row per run with its total weights.
+pub fn table(histories: &BTreeMap<String, Vec<Record>>) -> String {
+    let mut output = String::new();
+    for (name, records) in histories {
+        let columns = columns(records);
+        let _ = write!(output, "{name}\n{:<16}  {:<10}", "date", "commit");
+        for column in &columns {
+            let _ = write!(output, "  {column:>12}");
+        }
+        output.push('\n');
+        for record in records {
+            let commit = record.commit.as_deref().unwrap_or("-");
+            let commit = commit.get(..10).unwrap_or(commit);
+            let _ = write!(output, "{:<16}  {commit:<10}", date(record.timestamp));
+            for column in &columns {
+                let weight = record
+                    .weights
+                    .get(*column)
+                    .map_or("-".to_string(), ToString::to_string);
+                let _ = write!(output, "  {weight:>12}");
+            }
+            output.push('\n');
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// Render the histories as CSV with a row per run, missing weights are left empty.
+pub fn csv(histories: &BTreeMap<String, Vec<Record>>) -> String {
+    let columns = columns(histories.values().flatten());
+    let mut output = String::from("name,timestamp,commit");
+    for column in &columns {
+        let _ = write!(output, ",{column}");
+    }
+    output.push('\n');
+    for (name, records) in histories {
+        for record in records {
+            let commit = record.commit.as_deref().unwrap_or_default();
+            let _ = write!(output, "{name},{},{commit}", record.timestamp);
+            for column in &columns {
+                output.push(',');
+                if let Some(weight) = record.weights.get(*column) {
+                    let _ = write!(output, "{weight}");
+                }
+            }
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// UTC date and time of a Unix timestamp, e.g. `2024-05-01 12:30`.
+fn date(timestamp: u64) -> String {
+    let (days, seconds) = ((timestamp / 86400) as i64, timestamp % 86400);
+    // Civil date of the days since the epoch, from
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trend() {
+        assert_eq!(date(0), "1970-01-01 00:00");
+        assert_eq!(date(1_700_000_000), "2023-11-14 22:13");
+        assert_eq!(date(951_782_400), "2000-02-29 00:00");
+
+        let record = |timestamp, steps| Record {
+            timestamp,
+            commit: Some("0123456789abcdef".into()),
+            weights: BTreeMap::from([("steps".to_string(), steps)]),
+            builtins: BTreeMap::new(),
+        };
+        let histories = BTreeMap::from([("main".to_string(), vec![record(0, 10), record(60, 12)])]);
+        assert_eq!(
+            csv(&histories),
+            "name,timestamp,commit,steps\nmain,0,0123456789abcdef,10\n\
+            main,60,0123456789abcdef,12\n"
+        );
+        assert!(table(&histories).contains("1970-01-01 00:01  0123456789            12\n"));
+    }
+}
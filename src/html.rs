@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+
+use crate::burnprofile::Node;
+use crate::output;
+
+/// Page rendering the embedded profile with d3-flame-graph, which is loaded from a CDN.
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{TITLE}}</title>
+<link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/d3-flame-graph@4.1.3/dist/d3-flamegraph.css">
+<style>
+body { font-family: sans-serif; margin: 16px; }
+header { display: flex; gap: 8px; align-items: center; margin-bottom: 8px; }
+h1 { font-size: 18px; margin: 0 auto 0 0; }
+#details { min-height: 1.5em; font-size: 13px; }
+</style>
+</head>
+<body>
+<header>
+<h1>{{TITLE}}</h1>
+<input id="term" type="search" placeholder="Search frames">
+<button id="search">Search</button>
+<button id="clear">Clear</button>
+<button id="reset">Reset zoom</button>
+</header>
+<div id="chart"></div>
+<div id="details"></div>
+<script src="https://cdn.jsdelivr.net/npm/d3@7"></script>
+<script src="https://cdn.jsdelivr.net/npm/d3-flame-graph@4.1.3/dist/d3-flamegraph.min.js"></script>
+<script src="https://cdn.jsdelivr.net/npm/d3-flame-graph@4.1.3/dist/d3-flamegraph-tooltip.min.js"></script>
+<script>
+const data = {{DATA}};
+const total = data.value || 1;
+const chart = flamegraph()
+  .width(document.getElementById("chart").clientWidth)
+  .cellHeight(18)
+  .minFrameSize(1)
+  .setDetailsElement(document.getElementById("details"));
+chart.tooltip(flamegraph.tooltip.defaultFlamegraphTooltip().html(
+  (d) => `${d.data.name}: ${d.data.value} (${(100 * d.data.value / total).toFixed(2)}%)`
+));
+d3.select("#chart").datum(data).call(chart);
+const search = () => chart.search(document.getElementById("term").value);
+document.getElementById("search").onclick = search;
+document.getElementById("term").onkeydown = (e) => { if (e.key === "Enter") search(); };
+document.getElementById("clear").onclick = () => { document.getElementById("term").value = ""; chart.clear(); };
+document.getElementById("reset").onclick = () => chart.resetZoom();
+</script>
+</body>
+</html>
+"#;
+
+/// Render a folded profile as a self-contained interactive flamegraph page.
+pub fn page(folded: &str, title: &str) -> Result<String> {
+    Ok(TEMPLATE
+        .replace("{{TITLE}}", &escape(title))
+        .replace("{{DATA}}", &script_json(&Node::from_folded(folded))?))
+}
+
+/// Write a folded profile as a self-contained interactive flamegraph page.
+pub fn write(folded: &str, path: &Utf8PathBuf) -> Result<()> {
+    let title = path.file_stem().unwrap_or("scarb-burn");
+    output::write(path, page(folded, title)?)
+        .with_context(|| format!("failed to write HTML flamegraph: {path}"))
+}
+
+/// JSON to embed into a script element, which must not be closed by it.
+pub fn script_json(value: &impl serde::Serialize) -> Result<String> {
+    Ok(serde_json::to_string(value)?.replace("</", "<\\/"))
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
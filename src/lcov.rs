@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::listing::StatementWeights;
+use crate::output;
+use crate::source::SourceMap;
+
+/// Render the line coverage of a run in the LCOV tracefile format. Lines having a code location
+/// of any statement are instrumented, a line is hit once if any of its statements was executed.
+pub fn lcov(statements: &StatementWeights, source_map: &SourceMap) -> String {
+    let mut files: BTreeMap<&str, BTreeMap<u32, usize>> = BTreeMap::new();
+    for (idx, location) in &source_map.statements {
+        let hits = files
+            .entry(&location.file)
+            .or_default()
+            .entry(location.line)
+            .or_default();
+        if statements.weights.contains_key(idx) {
+            *hits = 1;
+        }
+    }
+
+    let mut output = String::new();
+    for (file, lines) in files {
+        let _ = writeln!(output, "TN:\nSF:{file}");
+        for (line, hits) in &lines {
+            let _ = writeln!(output, "DA:{line},{hits}");
+        }
+        let hit = lines.values().filter(|hits| **hits > 0).count();
+        let _ = writeln!(output, "LF:{}\nLH:{hit}\nend_of_record", lines.len());
+    }
+    output
+}
+
+/// Write the line coverage of a run as an LCOV tracefile.
+pub fn write(
+    statements: &StatementWeights,
+    source_map: &SourceMap,
+    path: &Utf8PathBuf,
+) -> Result<()> {
+    output::write(path, lcov(statements, source_map))
+        .with_context(|| format!("failed to write coverage: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::profiler;
+    use crate::source::Location;
+
+    #[test]
+    fn test_lcov() {
+        let (program, _) = profiler::falcon();
+        let location = |line| Location {
+            file: "/src/lib.cairo".into(),
+            line,
+        };
+        let source_map = SourceMap {
+            functions: HashMap::new(),
+            statements: HashMap::from([(0, location(2)), (1, location(2)), (2, location(5))]),
+        };
+        let statements = StatementWeights {
+            program,
+            weights: BTreeMap::from([(1, 10)]),
+        };
+        assert_eq!(
+            lcov(&statements, &source_map),
+            "TN:\nSF:/src/lib.cairo\nDA:2,1\nDA:5,0\nLF:2\nLH:1\nend_of_record\n"
+        );
+    }
+}
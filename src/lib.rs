@@ -0,0 +1,65 @@
+//! Profiler of Cairo programs behind `scarb burn`, for tools embedding it instead of running
+//! the binary, e.g. test runners, IDEs or benchmark harnesses:
+//!
+//! ```ignore
+//! let report = Profiler::new(program)
+//!     .with_function("main")
+//!     .with_args(args)
+//!     .with_weight_model(Weight::SierraGas)
+//!     .run()?;
+//! println!("{}", serde_json::to_string_pretty(&report)?);
+//! ```
+
+#[cfg(feature = "runner")]
+pub mod arguments;
+pub mod baseline;
+pub mod burnprofile;
+pub mod callgrind;
+pub mod chrome_trace;
+#[cfg(feature = "runner")]
+pub mod contract;
+pub mod dot;
+pub mod folded;
+#[cfg(feature = "runner")]
+pub mod fuzz;
+pub mod gecko;
+#[cfg(feature = "runner")]
+pub mod git;
+#[cfg(feature = "runner")]
+pub mod history;
+pub mod html;
+#[cfg(feature = "runner")]
+pub mod lcov;
+#[cfg(feature = "runner")]
+pub mod limits;
+#[cfg(feature = "runner")]
+pub mod listing;
+pub mod markdown;
+pub mod output;
+#[cfg(feature = "runner")]
+pub mod pprof_profile;
+#[cfg(feature = "runner")]
+pub mod profiler;
+pub mod report;
+#[cfg(feature = "runner")]
+pub mod rpc;
+#[cfg(feature = "runner")]
+mod runner;
+#[cfg(feature = "runner")]
+pub mod scarb;
+#[cfg(feature = "runner")]
+pub mod sink;
+pub mod snforge;
+pub mod source;
+pub mod speedscope;
+#[cfg(feature = "runner")]
+pub mod state;
+pub mod table;
+#[cfg(feature = "runner")]
+pub mod viewer;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+pub use report::ProfileReport;
+#[cfg(feature = "runner")]
+pub use runner::Profiler;
@@ -0,0 +1,128 @@
+use anyhow::{bail, ensure, Context, Result};
+use scarb_metadata::PackageMetadata;
+use std::collections::BTreeMap;
+
+/// Builtins which weren't used by a run are measured as zero.
+const BUILTINS: [&str; 12] = [
+    "output",
+    "range-check",
+    "pedersen",
+    "ecdsa",
+    "keccak",
+    "bitwise",
+    "ec-op",
+    "poseidon",
+    "segment-arena",
+    "range-check96",
+    "add-mod",
+    "mul-mod",
+];
+
+/// Cost budget of an entrypoint from `[tool.burn.limits]` in the package manifest, e.g.
+/// `main = { max-steps = 2_000_000, max-range-check = 50_000 }`.
+#[derive(Clone, Debug)]
+pub struct Limits {
+    pub entry: String,
+    /// Maximal totals by their name, a weight model like `sierra-gas` or a builtin.
+    pub limits: BTreeMap<String, usize>,
+}
+
+impl Limits {
+    /// Limits of the entrypoint, looked up by its path and then by its name.
+    pub fn from_manifest(package: &PackageMetadata, entry: &str) -> Result<Option<Self>> {
+        let Some(limits) = package
+            .tool_metadata("burn")
+            .and_then(|tool| tool.get("limits"))
+        else {
+            return Ok(None);
+        };
+        let name = entry.rsplit("::").next().unwrap_or(entry);
+        let Some(entry_limits) = limits.get(entry).or_else(|| limits.get(name)) else {
+            return Ok(None);
+        };
+        let limits = entry_limits
+            .as_object()
+            .with_context(|| format!("limits of `{entry}` have to be a table"))?
+            .iter()
+            .map(|(key, value)| {
+                let total = key.strip_prefix("max-").with_context(|| {
+                    format!("invalid limit `{key}` of `{entry}`, expected e.g. `max-steps`")
+                })?;
+                let value = value.as_u64().with_context(|| {
+                    format!("limit `{key}` of `{entry}` has to be a non-negative integer")
+                })?;
+                Ok((total.to_string(), value as usize))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Some(Self {
+            entry: entry.to_string(),
+            limits,
+        }))
+    }
+
+    /// Fail with all the exceeded limits, the totals are named like the limits.
+    pub fn check(&self, totals: &BTreeMap<String, usize>) -> Result<()> {
+        let mut violations = Vec::new();
+        for (name, limit) in &self.limits {
+            let total = match totals.get(name) {
+                Some(total) => *total,
+                None if BUILTINS.contains(&name.as_str()) => 0,
+                None => bail!(
+                    "limit `max-{name}` of `{}` can't be checked, measured totals: {}",
+                    self.entry,
+                    totals.keys().cloned().collect::<Vec<_>>().join(", ")
+                ),
+            };
+            if total > *limit {
+                violations.push(format!("{name} {total} > {limit}"));
+            }
+        }
+        ensure!(
+            violations.is_empty(),
+            "`{}` exceeded its limits: {}",
+            self.entry,
+            violations.join(", ")
+        );
+        Ok(())
+    }
+}
+
+/// Name of a builtin in the limits, e.g. `range-check` of `range_check_builtin`.
+pub fn builtin_name(builtin: &str) -> String {
+    builtin
+        .strip_suffix("_builtin")
+        .unwrap_or(builtin)
+        .replace('_', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let limits = Limits {
+            entry: "main".into(),
+            limits: BTreeMap::from([
+                ("steps".to_string(), 100),
+                (builtin_name("range_check_builtin"), 10),
+                ("poseidon".to_string(), 0),
+            ]),
+        };
+        let totals = |steps, range_checks| {
+            BTreeMap::from([
+                ("steps".to_string(), steps),
+                ("range-check".to_string(), range_checks),
+            ])
+        };
+        assert!(limits.check(&totals(100, 10)).is_ok());
+        let err = limits.check(&totals(120, 11)).unwrap_err().to_string();
+        assert_eq!(err, "`main` exceeded its limits: range-check 11 > 10, steps 120 > 100");
+
+        let limits = Limits {
+            entry: "main".into(),
+            limits: BTreeMap::from([("l2-gas".to_string(), 100)]),
+        };
+        assert!(limits.check(&totals(1, 1)).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use anyhow::{Context, Result};
+use cairo_lang_sierra::program::{Function, Program, Statement};
+use camino::Utf8PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+
+use crate::output;
+use crate::profiler::{self, Profile, ProfileOptions};
+use crate::source::{Location, SourceMap};
+
+/// Weights of the executed Sierra statements of a program, in the units of the weight model.
+/// Syscalls are not attributed to statements.
+pub struct StatementWeights {
+    pub program: Program,
+    pub weights: BTreeMap<usize, usize>,
+}
+
+impl StatementWeights {
+    pub fn new(program: Program, profiles: &[Profile], options: &ProfileOptions) -> Self {
+        let mut weights = BTreeMap::new();
+        for profile in profiles {
+            let info = &profile.info;
+            for (idx, steps) in info.sierra_statement_weights.iter().flat_map(|w| w.iter()) {
+                let libfunc = libfunc_name(&program, idx.0);
+                let weight = options.frame_weight(libfunc.as_deref(), *steps);
+                *weights.entry(idx.0).or_default() += weight;
+            }
+        }
+        Self { program, weights }
+    }
+
+    /// Add the weights of another run of the same program.
+    pub fn merge(&mut self, other: StatementWeights) {
+        for (idx, weight) in other.weights {
+            *self.weights.entry(idx).or_default() += weight;
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.weights.values().sum()
+    }
+}
+
+/// Concrete libfunc invoked by the statement, `None` for returns.
+fn libfunc_name(program: &Program, idx: usize) -> Option<String> {
+    match program.statements.get(idx)? {
+        Statement::Invocation(invocation) => Some(invocation.libfunc_id.to_string()),
+        Statement::Return(_) => None,
+    }
+}
+
+/// Functions by the index of their entry point statement.
+fn entry_points(program: &Program) -> HashMap<usize, Vec<&Function>> {
+    let mut entry_points: HashMap<usize, Vec<&Function>> = HashMap::new();
+    for func in &program.funcs {
+        entry_points.entry(func.entry_point.0).or_default().push(func);
+    }
+    entry_points
+}
+
+/// Render the Sierra program with the weight and the share of the total weight of every
+/// executed statement in front of it, the functions are marked at their entry points.
+pub fn sierra(statements: &StatementWeights) -> String {
+    let program = &statements.program;
+    let total = statements.total().max(1) as f64;
+    let entry_points = entry_points(program);
+
+    let mut output = format!("// Total weight {}\n\n", statements.total());
+    for declaration in &program.type_declarations {
+        let _ = writeln!(output, "{declaration};");
+    }
+    output.push('\n');
+    for declaration in &program.libfunc_declarations {
+        let _ = writeln!(output, "{declaration};");
+    }
+    for (idx, statement) in program.statements.iter().enumerate() {
+        for func in entry_points.get(&idx).into_iter().flatten() {
+            let _ = writeln!(output, "\n// {}", func.id);
+        }
+        let annotation = match statements.weights.get(&idx) {
+            Some(weight) => format!("{weight:>12} {:>6.2}%", 100.0 * *weight as f64 / total),
+            None => " ".repeat(20),
+        };
+        let _ = writeln!(output, "{annotation}  {statement}; // {idx}");
+    }
+    output.push('\n');
+    for func in &program.funcs {
+        let _ = writeln!(output, "{func};");
+    }
+    output
+}
+
+/// Compile the program to CASM and render its instructions grouped by the Sierra statements
+/// they were generated from, each group headed by the statement with its weight and share
+/// of the total weight. Statements compiled to no instructions are omitted.
+pub fn casm(statements: &StatementWeights) -> Result<String> {
+    let program = &statements.program;
+    let casm_program = profiler::compile_casm(program)?;
+    let total = statements.total().max(1) as f64;
+    let entry_points = entry_points(program);
+
+    let mut output = format!("// Total weight {}\n", statements.total());
+    let mut instructions = casm_program.instructions.iter().peekable();
+    let mut offset = 0;
+    let statement_info = &casm_program.debug_info.sierra_statement_info;
+    for (idx, (statement, info)) in program.statements.iter().zip(statement_info).enumerate() {
+        for func in entry_points.get(&idx).into_iter().flatten() {
+            let _ = writeln!(output, "\n// {}", func.id);
+        }
+        if info.end_offset == info.start_offset {
+            continue;
+        }
+        let annotation = match statements.weights.get(&idx) {
+            Some(weight) => format!("{weight:>12} {:>6.2}%", 100.0 * *weight as f64 / total),
+            None => " ".repeat(20),
+        };
+        let _ = writeln!(output, "{annotation}  // {idx}: {statement}");
+        while offset < info.end_offset {
+            let Some(instruction) = instructions.next() else {
+                break;
+            };
+            let _ = writeln!(output, "{:>22}{offset:>6}  {instruction};", "");
+            offset += instruction.body.op_size();
+        }
+    }
+    Ok(output)
+}
+
+/// Render the Cairo source files with the weights of their lines in the margin, the hottest
+/// files first. Lines are weighed by the statements generated from them, including the code
+/// inlined into other functions. Files which can't be read are rendered as their hot lines.
+pub fn source(statements: &StatementWeights, source_map: &SourceMap) -> String {
+    let mut files: BTreeMap<&str, BTreeMap<u32, usize>> = BTreeMap::new();
+    for (idx, weight) in &statements.weights {
+        if let Some(location) = source_map.statements.get(idx) {
+            let lines = files.entry(&location.file).or_default();
+            *lines.entry(location.line).or_default() += weight;
+        }
+    }
+    let mut files: Vec<_> = files
+        .into_iter()
+        .map(|(file, lines)| (file, lines.values().sum::<usize>(), lines))
+        .collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let total = statements.total().max(1) as f64;
+    let annotation = |weight: Option<&usize>| match weight {
+        Some(weight) => format!("{weight:>12} {:>6.2}%", 100.0 * *weight as f64 / total),
+        None => " ".repeat(20),
+    };
+    let mut output = format!("// Total weight {}\n", statements.total());
+    if files.is_empty() {
+        output.push_str(
+            "// No code locations, add `unstable-add-statements-code-locations-debug-info = true` \
+            to `[profile.dev.cairo]` in Scarb.toml\n",
+        );
+    }
+    for (file, weight, lines) in files {
+        let _ = writeln!(
+            output,
+            "\n// {file}: {weight} ({:.2}%)",
+            100.0 * weight as f64 / total
+        );
+        match fs::read_to_string(file) {
+            Ok(text) => {
+                for (idx, text_line) in text.lines().enumerate() {
+                    let line = idx as u32 + 1;
+                    let annotation = annotation(lines.get(&line));
+                    let _ = writeln!(output, "{annotation}  {line:>5}  {text_line}");
+                }
+            }
+            Err(_) => {
+                for (line, weight) in &lines {
+                    let _ = writeln!(output, "{}  {line:>5}", annotation(Some(weight)));
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Write the annotated Sierra listing.
+pub fn write_sierra(statements: &StatementWeights, path: &Utf8PathBuf) -> Result<()> {
+    output::write(path, sierra(statements))
+        .with_context(|| format!("failed to write annotated Sierra listing: {path}"))
+}
+
+/// Write the annotated Cairo sources.
+pub fn write_source(
+    statements: &StatementWeights,
+    source_map: &SourceMap,
+    path: &Utf8PathBuf,
+) -> Result<()> {
+    output::write(path, source(statements, source_map))
+        .with_context(|| format!("failed to write annotated sources: {path}"))
+}
+
+/// Write the annotated CASM listing.
+pub fn write_casm(statements: &StatementWeights, path: &Utf8PathBuf) -> Result<()> {
+    output::write(path, casm(statements)?)
+        .with_context(|| format!("failed to write annotated CASM listing: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sierra() {
+        let (program, _) = profiler::falcon();
+        let statements = StatementWeights {
+            program,
+            weights: BTreeMap::from([(0, 30), (2, 10)]),
+        };
+        let listing = sierra(&statements);
+        assert!(listing.starts_with("// Total weight 40\n"));
+        assert!(listing
+            .lines()
+            .any(|line| line.starts_with("          30  75.00%  ") && line.ends_with("; // 0")));
+        assert!(listing
+            .lines()
+            .any(|line| line.starts_with("                      ") && line.ends_with("; // 1")));
+    }
+
+    #[test]
+    fn test_source() {
+        let path = std::env::temp_dir().join("scarb_burn_test_source.cairo");
+        fs::write(&path, "fn main() {\n    foo();\n}\n").unwrap();
+        let file = path.to_string_lossy().to_string();
+        let location = |file: &str, line| Location {
+            file: file.to_string(),
+            line,
+        };
+        let source_map = SourceMap {
+            functions: HashMap::new(),
+            statements: HashMap::from([
+                (0, location(&file, 2)),
+                (1, location(&file, 2)),
+                (2, location("/missing.cairo", 7)),
+            ]),
+        };
+        let statements = StatementWeights {
+            program: profiler::falcon().0,
+            weights: BTreeMap::from([(0, 20), (1, 10), (2, 10)]),
+        };
+        let listing = source(&statements, &source_map);
+        assert!(listing.contains(&format!("// {file}: 30 (75.00%)")));
+        assert!(listing.contains("          30  75.00%      2      foo();"));
+        assert!(listing.contains("                          1  fn main() {"));
+        assert!(listing.contains("          10  25.00%      7\n"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_casm() {
+        let (program, _) = profiler::falcon();
+        let casm_program = profiler::compile_casm(&program).expect("failed to compile");
+        // Some statements, e.g. `disable_ap_tracking`, compile to no instructions
+        let idx = casm_program
+            .debug_info
+            .sierra_statement_info
+            .iter()
+            .position(|info| info.end_offset > info.start_offset)
+            .unwrap();
+        let statements = StatementWeights {
+            program,
+            weights: BTreeMap::from([(idx, 10)]),
+        };
+        let listing = casm(&statements).expect("failed to render CASM listing");
+        let header = format!("          10 100.00%  // {idx}: ");
+        let mut lines = listing.lines().skip_while(|line| !line.starts_with(&header));
+        assert!(lines.next().is_some());
+        assert!(lines.next().is_some_and(|line| line.ends_with(';')));
+    }
+}
@@ -1,224 +1,2090 @@
-mod profiler;
-
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
+use arguments::Literal;
 use cairo_lang_runner::Arg;
-use cairo_lang_sierra::program::VersionedProgram;
+use cairo_lang_sierra::program::{Program, VersionedProgram};
 use cairo_lang_utils::bigint::BigUintAsHex;
 use camino::Utf8PathBuf;
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+use inferno::differential;
 use inferno::flamegraph::{from_lines, Options};
-use num_bigint::BigInt;
-use std::collections::HashMap;
-use std::env;
+use limits::Limits;
+use listing::StatementWeights;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
+use std::io::{self, Read, Write};
 use std::process::{Command, ExitCode};
-use std::time::SystemTime;
+use std::sync::OnceLock;
 use webbrowser;
 
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use pprof::protos::Message;
-use pprof::{Frames, Report, Symbol};
-use scarb_metadata::{Metadata, MetadataCommand, ScarbCommand};
+use scarb_burn::{
+    arguments, baseline, burnprofile, callgrind, chrome_trace, contract, dot, folded, fuzz, gecko,
+    git, history, html, lcov, limits, listing, markdown, outln, output, pprof_profile, profiler,
+    report, rpc, scarb, sink, source, speedscope, state, table, viewer,
+};
+use pprof_profile::SampleType;
+use profiler::{CostTable, Dimension, GroupBy, Profile, ProfileOptions, Weight};
+use report::{
+    BuiltinReport, CallReport, CategoryReport, ClassReport, DeltaReport, EventReport, FeeReport,
+    FunctionReport, HotspotReport, LibfuncReport, LoopReport, MemoryReport, PathReport, Report,
+    RunMetadata, RunSummary, StorageReport, SyscallReport, VerifyReport,
+};
+use scarb::BuildArgs;
+use scarb_metadata::{Metadata, PackageMetadata};
 use scarb_ui::args::PackagesFilter;
+use sink::{ProfileSink, SinkProfile};
+use source::SourceMap;
+use state::StateFixture;
 
-#[derive(ValueEnum, Clone, Debug)]
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
 enum OutputType {
+    #[default]
     Flamegraph,
     Pprof,
+    /// JSON file for speedscope.app.
+    Speedscope,
+    /// Chrome trace event JSON for ui.perfetto.dev or chrome://tracing.
+    ChromeTrace,
+    /// Callgrind file for KCachegrind or QCachegrind.
+    Callgrind,
+    /// Searchable and zoomable flamegraph page.
+    Html,
+    /// Collapsed stacks text, `a;b;c 123` per line.
+    Folded,
+    /// Versioned call tree with the hashes of the program and the arguments, see README.
+    Burnprofile,
+    /// Inclusive and exclusive weights and calls per function as comma separated values.
+    Csv,
+    /// Same as csv with tab separated values.
+    Tsv,
+    /// Versioned JSON with totals, weights per function, builtin usages and the stacks tree.
+    Json,
+    /// Gecko profile JSON for profiler.firefox.com.
+    Firefox,
+    /// Graphviz call graph.
+    Dot,
+    /// Sierra program with the weight of every executed statement.
+    SierraAnnotated,
+    /// CASM instructions grouped by the Sierra statements with their weights.
+    CasmAnnotated,
+    /// Cairo source files with the weights of the lines, requires code locations debug info.
+    SourceAnnotated,
+    /// LCOV line coverage of the run, requires code locations debug info.
+    Lcov,
+    /// Call trace in the snforge `--save-trace-data` format, read by cairo-profiler.
+    SnforgeTrace,
 }
 
 /// Execute the main function of a package.
 #[derive(Parser, Clone, Debug)]
-#[command(author, version)]
+#[command(author, version, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Name of the package.
     #[command(flatten)]
     packages_filter: PackagesFilter,
 
-    /// Do not rebuild the package.
-    #[arg(long, default_value_t = false)]
-    no_build: bool,
+    #[command(flatten)]
+    build: BuildArgs,
+
+    #[command(flatten)]
+    run: RunArgs,
+
+    #[command(flatten)]
+    report: ReportArgs,
 
-    /// Serialized arguments to the executable function.
-    #[arg(long, value_delimiter = ',')]
+    /// Arguments to the function as comma separated Cairo literals, e.g. `1,-0x2,'short',[3,4]`.
+    /// Arrays, tuples and `"byte arrays"` are serialized for executables.
+    #[arg(long, allow_hyphen_values = true)]
     #[arg(long, conflicts_with_all = ["arguments_file", "profile_file"])]
-    arguments: Vec<BigInt>,
+    arguments: Vec<String>,
 
-    /// Serialized arguments to the executable function from a file.
+    /// Serialized arguments to the executable function from a file, `-` to read from stdin.
     #[arg(long, conflicts_with_all = ["arguments", "profile_file"])]
     arguments_file: Option<Utf8PathBuf>,
 
-    /// Use a scoped profile file instead of running the program.
+    /// Use a saved profile, a burnprofile or folded stacks, instead of running the program.
     #[arg(long, conflicts_with_all = ["arguments", "arguments_file"])]
     profile_file: Option<Utf8PathBuf>,
 
+    /// Profile a pre-built Sierra program, skips package resolution and build.
+    #[arg(long, conflicts_with = "profile_file")]
+    sierra_file: Option<Utf8PathBuf>,
+
+    /// Profile the Sierra program embedded into a Starknet contract class file.
+    #[arg(long, conflicts_with_all = ["profile_file", "sierra_file"])]
+    contract_class: Option<Utf8PathBuf>,
+
+    /// Treat the arguments file as an array of argument sets, run the function once per set
+    /// and aggregate the profiles.
+    #[arg(long, default_value_t = false, requires = "arguments_file")]
+    argument_sets: bool,
+
+    /// Also write a separate `set<N>.<output-file>` for every argument set.
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "argument_sets",
+        conflicts_with = "all_functions"
+    )]
+    output_per_set: bool,
+
+    /// Interpret arguments as values of the function parameters and serialize them
+    /// according to its signature, e.g. `["0x1", [1, 2], "text", {"low": 0, "high": 1}]`.
+    #[arg(long, default_value_t = false)]
+    typed: bool,
+
+    /// Function to profile, either a full path or a path suffix (e.g. `crypto::verify`).
+    #[arg(long, default_value = "main")]
+    function: String,
+
+    /// Profile every user function accepting the given arguments, each as a separate root frame.
+    #[arg(long, default_value_t = false, conflicts_with = "function")]
+    all_functions: bool,
+
+    /// Number of argument sets, functions or packages run in parallel, one per CPU by default.
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Profile the function with the given number of random inputs generated from its
+    /// signature, report the most expensive one and aggregate the profiles.
+    #[arg(
+        long,
+        value_name = "ITERATIONS",
+        conflicts_with_all = ["arguments", "arguments_file", "profile_file", "all_functions"]
+    )]
+    fuzz: Option<usize>,
+
+    /// Seed of the fuzzing input generator, random by default.
+    #[arg(long, requires = "fuzz")]
+    fuzz_seed: Option<u64>,
+
+    /// Use the arguments preset with the given name from `[tool.burn.scenarios]` in Scarb.toml.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "arguments",
+            "arguments_file",
+            "profile_file",
+            "sierra_file",
+            "contract_class",
+            "fuzz",
+        ]
+    )]
+    scenario: Option<String>,
+
+    /// Arguments of the selected scenario in the arguments file format.
+    #[arg(skip)]
+    scenario_arguments: Option<serde_json::Value>,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum Commands {
+    /// Profile a single test function.
+    Test(TestArgs),
+    /// Profile an external function of a Starknet contract.
+    Contract(ContractArgs),
+    /// List functions which can be profiled.
+    List(ListArgs),
+    /// Render a summary of a folded profile, e.g. for a pull request comment.
+    Report(SummaryArgs),
+    /// Compare two folded profiles or the working tree with a git ref.
+    Diff(DiffArgs),
+    /// Find the commit where the total weight crossed a threshold.
+    Bisect(BisectArgs),
+    /// Print the total weights of the runs recorded with `--record-history`.
+    Trend(TrendArgs),
+    /// Sum the weights of several folded profiles into one.
+    Merge(MergeArgs),
+    /// Render a saved profile in another output format without running the program.
+    Convert(ConvertArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct TestArgs {
+    /// Name of the test or a part of it, has to match exactly one test.
+    filter: String,
+
+    /// Name of the package.
+    #[command(flatten)]
+    packages_filter: PackagesFilter,
+
+    #[command(flatten)]
+    build: BuildArgs,
+
+    #[command(flatten)]
+    run: RunArgs,
+
+    #[command(flatten)]
+    report: ReportArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct ContractArgs {
+    /// Name or module path of the contract, can be omitted if the package has a single contract.
+    #[arg(long)]
+    contract: Option<String>,
+
+    /// Name or hex selector of the external function.
+    #[arg(long)]
+    entrypoint: String,
+
+    /// Use a pre-built contract class file, skips package resolution and build.
+    #[arg(long, conflicts_with = "contract")]
+    contract_class: Option<Utf8PathBuf>,
+
+    /// Calldata of the external function as comma separated Cairo literals,
+    /// arrays, tuples and `"byte arrays"` are serialized.
+    #[arg(long, allow_hyphen_values = true)]
+    calldata: Vec<String>,
+
+    /// Named argument as `name=value` with a Cairo literal value, matched against
+    /// the parameters of the external function in the contract ABI.
+    #[arg(
+        long = "arg",
+        value_name = "NAME=VALUE",
+        allow_hyphen_values = true,
+        conflicts_with = "calldata"
+    )]
+    named_args: Vec<String>,
+
+    /// Use the calldata of the entrypoint call made by an on-chain transaction.
+    #[arg(
+        long,
+        value_name = "TX_HASH",
+        conflicts_with_all = ["calldata", "named_args"],
+        requires = "rpc_url"
+    )]
+    from_tx: Option<String>,
+
+    /// Starknet JSON-RPC endpoint to fetch the transaction from.
+    #[arg(long, env = "STARKNET_RPC_URL")]
+    rpc_url: Option<String>,
+
+    /// Print the size of the contract class and the estimated declare fee.
+    #[arg(long, default_value_t = false)]
+    class_size: bool,
+
+    /// Export the class size report as JSON.
+    #[arg(long)]
+    class_size_file: Option<Utf8PathBuf>,
+
+    /// Compare the resources of the run with the receipt of an on-chain transaction.
+    #[arg(long, value_name = "TX_HASH", requires = "rpc_url")]
+    verify_against_tx: Option<String>,
+
+    /// Export the comparison with the receipt as JSON.
+    #[arg(long, requires = "verify_against_tx")]
+    verify_file: Option<Utf8PathBuf>,
+
+    /// Print the estimated transaction fee with a breakdown per component.
+    #[arg(long, default_value_t = false)]
+    fee: bool,
+
+    /// Export the fee estimate as JSON.
+    #[arg(long)]
+    fee_file: Option<Utf8PathBuf>,
+
+    /// L2 gas price in FRI used for the fee estimate.
+    #[arg(long, default_value_t = 8_000_000_000)]
+    l2_gas_price: u128,
+
+    /// L1 data gas price in FRI used for the fee estimate.
+    #[arg(long, default_value_t = 1_000_000)]
+    l1_data_gas_price: u128,
+
+    /// Name of the package.
+    #[command(flatten)]
+    packages_filter: PackagesFilter,
+
+    #[command(flatten)]
+    build: BuildArgs,
+
+    #[command(flatten)]
+    run: RunArgs,
+
+    #[command(flatten)]
+    report: ReportArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SummaryFormat {
+    /// GitHub-flavored Markdown.
+    Markdown,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct SummaryArgs {
+    /// Folded profile to summarize, e.g. written with `--output-type folded`.
+    profile_file: Utf8PathBuf,
+
+    /// Folded profile to compare with.
+    #[arg(long)]
+    baseline: Option<Utf8PathBuf>,
+
+    /// Format of the summary.
+    #[arg(long, value_enum, default_value_t = SummaryFormat::Markdown)]
+    format: SummaryFormat,
+
+    /// Number of top functions in the summary.
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// Write the summary to a file instead of the standard output.
+    #[arg(long)]
+    output_file: Option<Utf8PathBuf>,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct DiffArgs {
+    /// Folded profile to compare, e.g. written with `--output-type folded`.
+    #[arg(
+        required_unless_present_any = ["git_ref", "inputs"],
+        conflicts_with_all = ["git_ref", "inputs"]
+    )]
+    profile_file: Option<Utf8PathBuf>,
+
+    /// Folded profile to compare with, frames are colored red where the weight increased
+    /// and blue where it decreased.
+    #[arg(
+        long,
+        required_unless_present_any = ["git_ref", "inputs"],
+        conflicts_with_all = ["git_ref", "inputs"]
+    )]
+    base: Option<Utf8PathBuf>,
+
+    /// Profile the working tree and the given git ref, checked out in a temporary worktree,
+    /// and compare them.
+    #[arg(long = "ref", value_name = "REF", conflicts_with = "inputs")]
+    git_ref: Option<String>,
+
+    /// Profile the program with two arguments files and compare the runs, e.g. a small
+    /// and a large input to see which code paths scale badly.
+    #[arg(long, num_args = 2, value_names = ["BASE", "CURRENT"])]
+    inputs: Vec<Utf8PathBuf>,
+
+    /// Arguments of both profile runs with `--ref` or `--inputs`, e.g. `-- --function foo`.
+    #[arg(last = true)]
+    profile_args: Vec<String>,
+
+    /// Scale the base profile to the total weight of the compared one, so that only
+    /// the distribution of the weight is compared.
+    #[arg(long, default_value_t = false)]
+    normalize: bool,
+
+    /// Fail if the total weight increased by more than the given percentage.
+    #[arg(long)]
+    max_regression: Option<f64>,
+
+    /// Number of the most changed functions in the delta table.
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    /// Match the frames by their exact names, by default the generic arguments and the ids of
+    /// the loop functions are ignored as they change between builds.
+    #[arg(long, default_value_t = false)]
+    exact_names: bool,
+
+    /// Output file path.
+    #[arg(long, default_value = "diff.svg")]
+    output_file: Utf8PathBuf,
+
+    /// Open the differential flamegraph in browser.
+    #[arg(long, default_value_t = false)]
+    open_in_browser: bool,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct ConvertArgs {
+    /// Saved profile: folded stacks, a burnprofile or a pprof profile.
+    profile_file: Utf8PathBuf,
+
+    /// Output file type.
+    #[arg(long, value_enum)]
+    to: OutputType,
+
+    /// Path to write the output file, `-` for the standard output, defaults as `--output-file`.
+    #[arg(short, long)]
+    output_file: Option<Utf8PathBuf>,
+
+    /// Open the output file in browser like `--open-in-browser`.
+    #[arg(long, default_value_t = false)]
+    open_in_browser: bool,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct MergeArgs {
+    /// Folded profiles to merge, e.g. of different argument sets or CI shards.
+    #[arg(required = true)]
+    profile_files: Vec<Utf8PathBuf>,
+
+    /// Output file path of the merged profile, `-` for the standard output.
+    #[arg(short, long)]
+    output_file: Utf8PathBuf,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum TrendFormat {
+    /// Table per profile.
+    Table,
+    /// CSV with a row per run.
+    Csv,
+    /// JSON object of the runs by profile.
+    Json,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct TrendArgs {
+    /// Name of the profile, e.g. of the package or the entrypoint, all profiles by default.
+    name: Option<String>,
+
+    /// Format of the time series.
+    #[arg(long, value_enum, default_value_t = TrendFormat::Table)]
+    format: TrendFormat,
+
+    /// Write the time series to a file instead of the standard output.
+    #[arg(long)]
+    output_file: Option<Utf8PathBuf>,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct BisectArgs {
+    /// Commit whose total weight is at most the threshold.
+    #[arg(long)]
+    good: String,
+
+    /// Commit whose total weight is above the threshold.
+    #[arg(long, default_value = "HEAD")]
+    bad: String,
+
+    /// Total weight separating the good commits from the bad ones.
+    #[arg(long)]
+    threshold: usize,
+
+    /// Arguments of the profile runs, e.g. `-- --function foo` or `-- test foo`.
+    #[arg(last = true)]
+    profile_args: Vec<String>,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct ListArgs {
+    /// Name of the package.
+    #[command(flatten)]
+    packages_filter: PackagesFilter,
+
+    /// List functions of a pre-built Sierra program, skips package resolution and build.
+    #[arg(long)]
+    sierra_file: Option<Utf8PathBuf>,
+
+    #[command(flatten)]
+    build: BuildArgs,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct RunArgs {
+    /// Gas budget of gas-enabled programs instead of unlimited gas,
+    /// if it runs out the profile shows where execution stopped.
+    #[arg(long)]
+    available_gas: Option<usize>,
+
+    /// Stop gas-enabled programs at this many steps, the profile shows where.
+    #[arg(long)]
+    max_steps: Option<usize>,
+
+    /// Breakdowns of the profile, several ones are put under their own root frames.
+    #[arg(
+        long = "by",
+        value_enum,
+        value_delimiter = ',',
+        default_value = "scoped-statement"
+    )]
+    dimensions: Vec<Dimension>,
+
+    /// Meaning of the weights: CASM steps, Sierra gas, L2 gas, prover trace cells, memory cells
+    /// or builtin usages.
+    #[arg(long, value_enum, default_value_t = Weight::Steps)]
+    weight: Weight,
+
+    /// TOML file with `step`, `[builtins]`, `[libfuncs]` and `[syscalls]` costs overriding
+    /// the weight model.
+    #[arg(long)]
+    cost_table: Option<Utf8PathBuf>,
+
+    /// TOML file with the Starknet state to run against, e.g. the caller address.
+    #[arg(long)]
+    starknet_state: Option<Utf8PathBuf>,
+
+    /// Address returned by `get_caller_address`, overrides the Starknet state file.
+    /// The caller is the only execution info the runner lets be set from the command line.
+    #[arg(long)]
+    caller_address: Option<String>,
+
+    /// Move the cost of squashing dicts to the functions accessing them
+    /// instead of the place the dicts are destructed.
+    #[arg(long, default_value_t = false)]
+    attribute_dict_squash: bool,
+
+    /// Merge the instantiations of generic functions and libfuncs
+    /// by stripping their type arguments.
+    #[arg(long, default_value_t = false)]
+    collapse_generics: bool,
+
+    /// Collapse the frames to crates, modules or functions, merging libfuncs into their callers.
+    #[arg(long, value_enum)]
+    group_by: Option<GroupBy>,
+
+    /// Weight the frames by the size of the compiled CASM bytecode instead of running,
+    /// a contract is rendered as a whole.
+    #[arg(long, default_value_t = false)]
+    code_size: bool,
+
+    /// Prune frames with a lower weight, both when processing and when rendering.
+    #[arg(long, default_value_t = 1)]
+    min_weight: usize,
+}
+
+impl RunArgs {
+    /// Starknet state of the state file with the addresses given on the command line.
+    fn starknet_state(&self) -> Result<Option<StateFixture>> {
+        let mut state = self
+            .starknet_state
+            .as_ref()
+            .map(StateFixture::load)
+            .transpose()?;
+        if let Some(address) = &self.caller_address {
+            let fixture = state.get_or_insert_with(Default::default);
+            fixture.caller_address = Some(address.clone());
+            fixture
+                .starknet_state()
+                .with_context(|| "invalid `--caller-address`")?;
+        }
+        Ok(state)
+    }
+
+    fn options(&self) -> Result<ProfileOptions> {
+        Ok(ProfileOptions {
+            available_gas: self.available_gas,
+            max_steps: self.max_steps,
+            dimensions: self.dimensions.clone(),
+            weight: self.weight,
+            cost_table: self.cost_table.as_ref().map(CostTable::load).transpose()?,
+            weight_model: None,
+            starknet_state: self.starknet_state()?,
+            attribute_dict_squash: self.attribute_dict_squash,
+            collapse_generics: self.collapse_generics,
+            group_by: self.group_by,
+            min_weight: self.min_weight,
+        })
+    }
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct ReportArgs {
+    /// Print the top functions by self weight with their share of the total after the run.
+    #[arg(long, default_value_t = false)]
+    report: bool,
+
+    /// Print builtin usage along with the top functions using each builtin.
+    #[arg(long, default_value_t = false)]
+    builtins: bool,
+
+    /// Export the builtin usage report as JSON.
+    #[arg(long)]
+    builtins_file: Option<Utf8PathBuf>,
+
+    /// Print memory usage: cells, holes, builtin segments and dict accesses.
+    #[arg(long, default_value_t = false)]
+    memory: bool,
+
+    /// Export the memory usage report as JSON.
+    #[arg(long)]
+    memory_file: Option<Utf8PathBuf>,
+
+    /// Print the top concrete libfuncs by steps along with their estimated invocation counts.
+    #[arg(long, default_value_t = false)]
+    libfuncs: bool,
+
+    /// Export the libfunc report as JSON.
+    #[arg(long)]
+    libfuncs_file: Option<Utf8PathBuf>,
+
+    /// Print the top functions by inclusive weight along with their exclusive weight.
+    #[arg(long, default_value_t = false)]
+    functions: bool,
+
+    /// Export the inclusive and exclusive weights of all functions as JSON.
+    #[arg(long)]
+    functions_file: Option<Utf8PathBuf>,
+
+    /// Print the syscalls of contract runs along with their gas cost.
+    #[arg(long, default_value_t = false)]
+    syscalls: bool,
+
+    /// Export the syscall report as JSON.
+    #[arg(long)]
+    syscalls_file: Option<Utf8PathBuf>,
+
+    /// Print the emitted events along with the gas of the syscalls.
+    #[arg(long, default_value_t = false)]
+    events: bool,
+
+    /// Export the event report as JSON.
+    #[arg(long)]
+    events_file: Option<Utf8PathBuf>,
+
+    /// Print the storage variables or functions with the most storage reads and writes.
+    #[arg(long, default_value_t = false)]
+    storage: bool,
+
+    /// Export the storage reads and writes per variable or function as JSON.
+    #[arg(long)]
+    storage_file: Option<Utf8PathBuf>,
+
+    /// Print the top functions by call count along with their weight per call.
+    #[arg(long, default_value_t = false)]
+    counts: bool,
+
+    /// Export the call counts per function and call stack as JSON.
+    #[arg(long)]
+    counts_file: Option<Utf8PathBuf>,
+
+    /// Print the hottest loops along with their iterations and cost per iteration.
+    #[arg(long, default_value_t = false)]
+    loops: bool,
+
+    /// Export the loops of all call stacks as JSON.
+    #[arg(long)]
+    loops_file: Option<Utf8PathBuf>,
+
+    /// Print the weight shares of libfunc categories (arithmetic, hashing, memory, ...)
+    /// of the top-level functions.
+    #[arg(long, default_value_t = false)]
+    categories: bool,
+
+    /// Export the libfunc category weights of all top-level functions as JSON.
+    #[arg(long)]
+    categories_file: Option<Utf8PathBuf>,
+
+    /// Print the K most expensive call chains along with their weights.
+    #[arg(long, value_name = "K")]
+    top_paths: Option<usize>,
+
+    /// Number of top entries shown in the printed reports.
+    #[arg(long, default_value_t = 5)]
+    top: usize,
+}
+
+impl ReportArgs {
+    /// Claim the standard output for the reports exported to `-`, see [`output::claim`].
+    fn claim_stdout(&self) {
+        let files = [
+            &self.builtins_file,
+            &self.memory_file,
+            &self.libfuncs_file,
+            &self.functions_file,
+            &self.syscalls_file,
+            &self.events_file,
+            &self.storage_file,
+            &self.counts_file,
+            &self.loops_file,
+            &self.categories_file,
+        ];
+        files.into_iter().flatten().for_each(output::claim);
+    }
+}
+
+#[derive(clap::Args, Clone, Debug, Default)]
+struct OutputArgs {
     /// Output file type
     #[arg(long, value_enum, default_value_t = OutputType::Flamegraph)]
     output_type: OutputType,
 
-    /// Path to write the output file.
+    /// Path to write the output file, `-` for the standard output
+    /// [default: flamegraph.svg, profile.pb.gz, profile.speedscope.json, trace.json,
+    /// callgrind.out, flamegraph.html, profile.folded, profile.burnprofile, functions.csv,
+    /// functions.tsv, profile.json, profile.gecko.json, callgraph.dot, profile.sierra,
+    /// profile.casm, source.txt, coverage.lcov or trace.snforge.json]
     #[arg(long)]
-    output_file: Utf8PathBuf,
+    output_file: Option<Utf8PathBuf>,
+
+    /// Write an output of the given type to the given path, e.g. `--output pprof=profile.pb.gz`,
+    /// can be repeated to write several formats from a single run.
+    #[arg(
+        long = "output",
+        value_name = "TYPE=PATH",
+        value_parser = parse_output,
+        conflicts_with_all = ["output_type", "output_file"]
+    )]
+    outputs: Vec<(OutputType, Utf8PathBuf)>,
 
     /// Open output in browser:
-    /// - For flamegraph: opens the SVG file directly
-    /// - For pprof: starts a pprof web server on port 8000 (requires Go toolchain installed)
+    /// - For flamegraph and html: opens the file directly
+    /// - For pprof: starts a pprof web server on port 8000, the built-in one without Go toolchain
+    /// - For speedscope: opens speedscope.app to drop the file into
+    /// - For chrome-trace: opens ui.perfetto.dev to open the file in
+    /// - For callgrind: starts KCachegrind (requires it installed)
+    /// - For firefox: opens the Firefox Profiler to load the file in
     #[arg(long, default_value_t = false)]
     open_in_browser: bool,
+
+    /// Merge the stacks from the leaves in the flamegraph, showing which libfuncs dominate
+    /// across all call sites.
+    #[arg(long, default_value_t = false)]
+    inverted: bool,
+
+    /// Draw the flamegraph as an icicle graph, growing downwards from the root.
+    #[arg(long, default_value_t = false)]
+    icicle: bool,
+
+    /// Append a Markdown summary with the total weight and the top functions to
+    /// `$GITHUB_STEP_SUMMARY`, for a profiling step in GitHub Actions.
+    #[arg(long, default_value_t = false)]
+    gha_summary: bool,
+
+    /// Saved profile to compare with in the job summary, e.g. folded stacks or a burnprofile,
+    /// the saved baseline by default.
+    #[arg(long, requires = "gha_summary")]
+    summary_baseline: Option<Utf8PathBuf>,
+
+    /// Lay out the flamegraph stacks from left to right in the order of execution instead of
+    /// merging them alphabetically. Repeated stacks are drawn where they were first executed.
+    #[arg(long, default_value_t = false, conflicts_with = "inverted")]
+    flamechart: bool,
+
+    /// Save the profile as the baseline in `.burn/baseline`, later runs print the change
+    /// of the total weight relative to it.
+    #[arg(long, default_value_t = false)]
+    save_baseline: bool,
+
+    /// Append the total weights of the run with the current commit to `.burn/history`,
+    /// see `scarb burn trend`.
+    #[arg(long, default_value_t = false)]
+    record_history: bool,
+}
+
+impl OutputArgs {
+    /// Claim the standard output for the outputs written to `-`, see [`output::claim`].
+    fn claim_stdout(&self) {
+        if self.outputs.is_empty() {
+            output::claim(&self.output_file());
+        }
+        for (_, path) in &self.outputs {
+            output::claim(path);
+        }
+    }
+
+    /// Whether any of the outputs is a listing annotated with the weights of the statements.
+    fn uses_statements(&self) -> bool {
+        let uses_statements = |output_type| {
+            matches!(
+                output_type,
+                OutputType::SierraAnnotated
+                    | OutputType::CasmAnnotated
+                    | OutputType::SourceAnnotated
+                    | OutputType::Lcov
+            )
+        };
+        if self.outputs.is_empty() {
+            uses_statements(self.output_type)
+        } else {
+            self.outputs
+                .iter()
+                .any(|(output_type, _)| uses_statements(*output_type))
+        }
+    }
+
+    /// Outputs with the file names prefixed, e.g. with the package name.
+    fn with_prefix(&self, prefix: &str) -> Self {
+        let mut output = self.clone();
+        output.output_file = Some(prefixed_output_file(&self.output_file(), prefix));
+        for (_, path) in &mut output.outputs {
+            *path = prefixed_output_file(path, prefix);
+        }
+        output
+    }
+
+    fn output_file(&self) -> Utf8PathBuf {
+        self.output_file
+            .clone()
+            .unwrap_or_else(|| match self.output_type {
+                OutputType::Flamegraph => "flamegraph.svg".into(),
+                OutputType::Pprof => "profile.pb.gz".into(),
+                OutputType::Speedscope => "profile.speedscope.json".into(),
+                OutputType::ChromeTrace => "trace.json".into(),
+                OutputType::Callgrind => "callgrind.out".into(),
+                OutputType::Html => "flamegraph.html".into(),
+                OutputType::Folded => "profile.folded".into(),
+                OutputType::Burnprofile => "profile.burnprofile".into(),
+                OutputType::Csv => "functions.csv".into(),
+                OutputType::Tsv => "functions.tsv".into(),
+                OutputType::Json => "profile.json".into(),
+                OutputType::Firefox => "profile.gecko.json".into(),
+                OutputType::Dot => "callgraph.dot".into(),
+                OutputType::SierraAnnotated => "profile.sierra".into(),
+                OutputType::CasmAnnotated => "profile.casm".into(),
+                OutputType::SourceAnnotated => "source.txt".into(),
+                OutputType::Lcov => "coverage.lcov".into(),
+                OutputType::SnforgeTrace => "trace.snforge.json".into(),
+            })
+    }
+
+    fn sink(&self) -> Box<dyn ProfileSink> {
+        match self.output_type {
+            OutputType::Flamegraph => Box::new(sink::Flamegraph {
+                inverted: self.inverted,
+                flamechart: self.flamechart,
+                icicle: self.icicle,
+            }),
+            OutputType::Pprof => Box::new(sink::Pprof),
+            OutputType::Speedscope => Box::new(sink::Speedscope),
+            OutputType::ChromeTrace => Box::new(sink::ChromeTrace),
+            OutputType::Callgrind => Box::new(sink::Callgrind),
+            OutputType::Html => Box::new(sink::Html),
+            OutputType::Folded => Box::new(sink::Folded),
+            OutputType::Burnprofile => Box::new(sink::Burnprofile),
+            OutputType::Csv => Box::new(sink::Table(',')),
+            OutputType::Tsv => Box::new(sink::Table('\t')),
+            OutputType::Json => Box::new(sink::Json),
+            OutputType::Firefox => Box::new(sink::Firefox),
+            OutputType::Dot => Box::new(sink::Dot),
+            OutputType::SierraAnnotated => Box::new(sink::SierraListing),
+            OutputType::CasmAnnotated => Box::new(sink::CasmListing),
+            OutputType::SourceAnnotated => Box::new(sink::SourceListing),
+            OutputType::Lcov => Box::new(sink::Lcov),
+            OutputType::SnforgeTrace => Box::new(sink::SnforgeTrace),
+        }
+    }
+}
+
+fn parse_output(value: &str) -> Result<(OutputType, Utf8PathBuf), String> {
+    let (output_type, path) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected TYPE=PATH, got `{value}`"))?;
+    let output_type = OutputType::from_str(output_type, true)?;
+    Ok((output_type, path.into()))
 }
 
 fn main() -> ExitCode {
     let args: Args = Args::parse();
     if let Err(err) = main_inner(args) {
-        println!("\x1b[1;31m(•͡˘_•͡˘)ノð\x1b[0m {err:#}");
+        outln!("\x1b[1;31m(•͡˘_•͡˘)ノð\x1b[0m {err:#}");
         return ExitCode::FAILURE;
     }
     ExitCode::SUCCESS
 }
 
 fn main_inner(args: Args) -> Result<()> {
-    let result = if let Some(path) = &args.profile_file {
-        std::fs::read_to_string(path)
-        .with_context(|| format!("failed to read profile file at {}", path))?
+    match &args.command {
+        Some(Commands::Test(test_args)) => {
+            test_args.report.claim_stdout();
+            test_args.output.claim_stdout();
+        }
+        Some(Commands::Contract(contract_args)) => {
+            contract_args.report.claim_stdout();
+            contract_args.output.claim_stdout();
+            let files = [
+                &contract_args.class_size_file,
+                &contract_args.verify_file,
+                &contract_args.fee_file,
+            ];
+            files.into_iter().flatten().for_each(output::claim);
+        }
+        None => {
+            args.report.claim_stdout();
+            args.output.claim_stdout();
+        }
+        _ => {}
+    }
+    match args.command {
+        Some(Commands::Test(test_args)) => {
+            let result = profile_test(&test_args)?;
+            write_results(&result, &test_args.output, &test_args.filter)
+        }
+        Some(Commands::Contract(contract_args)) => {
+            let result = profile_contract(&contract_args)?;
+            write_results(&result, &contract_args.output, &contract_args.entrypoint)
+        }
+        Some(Commands::List(list_args)) => list_functions(&list_args),
+        Some(Commands::Report(summary_args)) => summarize(&summary_args),
+        Some(Commands::Diff(diff_args)) => diff(&diff_args),
+        Some(Commands::Bisect(bisect_args)) => bisect(&bisect_args),
+        Some(Commands::Trend(trend_args)) => trend(&trend_args),
+        Some(Commands::Merge(merge_args)) => merge(&merge_args),
+        Some(Commands::Convert(convert_args)) => convert(&convert_args),
+        None => run(&args),
+    }
+}
+
+/// Profile the program given explicitly or every package matching the filter.
+fn run(args: &Args) -> Result<()> {
+    if let Some(jobs) = args.jobs {
+        ensure!(jobs > 0, "`--jobs` must be at least 1");
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .with_context(|| "failed to start the thread pool")?;
+    }
+    if let Some(path) = &args.profile_file {
+        let result = burnprofile::load(path)?;
+        return write_results(&result.into(), &args.output, path.as_str());
+    }
+
+    let program = if let Some(path) = &args.contract_class {
+        let contract_class = contract::load_contract_class(path)?;
+        Some((contract::extract_program(&contract_class)?, SourceMap::default()))
+    } else if let Some(path) = &args.sierra_file {
+        Some(load_artifact(path)?)
     } else {
-        let metadata = MetadataCommand::new().inherit_stderr().exec()?;
-        let package = args.packages_filter.match_one(&metadata)?;
-
-        let program_args: Vec<Arg> = if let Some(path) = args.arguments_file {
-            let file = fs::File::open(&path).with_context(|| "reading arguments file failed")?;
-            let as_vec: Vec<BigUintAsHex> =
-                serde_json::from_reader(file).with_context(|| "deserializing arguments file failed")?;
-            as_vec
-                .into_iter()
-                .map(|v| Arg::Value(v.value.into()))
-                .collect()
-        } else {
-            args.arguments
-                .iter()
-                .map(|v| Arg::Value(v.into()))
+        None
+    };
+    if let Some((program, locations)) = program {
+        let result = profile_program(args, program)?.with_locations(locations);
+        return write_results(&result, &args.output, &args.function);
+    }
+
+    let metadata = scarb::metadata(&args.build)?;
+    let packages = args.packages_filter.match_many(&metadata)?;
+    if let [package] = packages.as_slice() {
+        let args = with_scenario(args, package)?;
+        let path = package_sierra_file(&metadata, package, &args.build)?;
+        let (program, locations) = load_artifact(&path)?;
+        let result = profile_program(&args, program)?
+            .with_locations(locations)
+            .with_limits(Limits::from_manifest(package, &args.function)?)
+            .with_package(Some(package));
+        return write_results(&result, &args.output, &package.name);
+    }
+    profile_workspace(args, &metadata, &packages)
+}
+
+/// Profile packages one by one, write an output file per package and print a summary.
+fn profile_workspace(args: &Args, metadata: &Metadata, packages: &[PackageMetadata]) -> Result<()> {
+    // Builds print the output of Scarb, so the packages are built in order before they run in
+    // parallel, and their outputs are written in order once all of them completed
+    let programs: Vec<Result<_>> = packages
+        .iter()
+        .map(|package| {
+            outln!("Profiling package {}", package.name);
+            let args = with_scenario(args, package)?;
+            let path = package_sierra_file(metadata, package, &args.build)?;
+            let (program, locations) = load_artifact(&path)?;
+            Ok((args, program, locations))
+        })
+        .collect();
+    let runs: Vec<Result<_>> = programs
+        .into_par_iter()
+        .map(|program| {
+            let (args, program, locations) = program?;
+            let runs = run_program(&args, &program)?;
+            Ok((args, program, locations, runs))
+        })
+        .collect();
+    let total_weights: Vec<_> = packages
+        .iter()
+        .zip(runs)
+        .map(|(package, runs)| {
+            let total_weight = runs.and_then(|(args, program, locations, runs)| {
+                let result = write_program(&args, program, runs)?
+                    .with_locations(locations)
+                    .with_limits(Limits::from_manifest(package, &args.function)?)
+                    .with_package(Some(package));
+                write_results(
+                    &result,
+                    &args.output.with_prefix(&package.name),
+                    &package.name,
+                )?;
+                Ok(folded::total_weight(&result.folded))
+            });
+            if let Err(err) = &total_weight {
+                outln!("Failed to profile {}: {err:#}", package.name);
+            }
+            total_weight.ok()
+        })
+        .collect();
+    let summary: Vec<_> = packages
+        .iter()
+        .map(|package| package.name.as_str())
+        .zip(total_weights)
+        .collect();
+
+    let width = summary
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(["Package".len()])
+        .max()
+        .unwrap_or_default();
+    outln!("{:<width$}  {:>14}", "Package", "Total weight");
+    for (name, total_weight) in &summary {
+        match total_weight {
+            Some(total_weight) => outln!("{name:<width$}  {total_weight:>14}"),
+            None => outln!("{name:<width$}  {:>14}", "failed"),
+        }
+    }
+
+    let failed = summary
+        .iter()
+        .filter(|(_, weight)| weight.is_none())
+        .count();
+    ensure!(failed == 0, "failed to profile {failed} package(s)");
+    Ok(())
+}
+
+/// Resolve `--scenario` against the package manifest, scenarios are either a string
+/// of Cairo literals or an array in the arguments file format.
+fn with_scenario(args: &Args, package: &PackageMetadata) -> Result<Args> {
+    let mut args = args.clone();
+    if let Some(name) = &args.scenario {
+        match scarb::scenario(package, name)? {
+            serde_json::Value::String(literals) => args.arguments = vec![literals],
+            input => args.scenario_arguments = Some(input),
+        }
+    }
+    Ok(args)
+}
+
+/// Arguments of every run, there is more than one set only with `--argument-sets`.
+fn program_arg_sets(args: &Args, program: &Program, function: &str) -> Result<Vec<Vec<Arg>>> {
+    match &args.arguments_file {
+        Some(path) if args.argument_sets => {
+            let sets: Vec<serde_json::Value> = serde_json::from_str(&read_arguments_file(path)?)
+                .with_context(|| "deserializing arguments file failed")?;
+            sets.into_iter()
+                .enumerate()
+                .map(|(idx, set)| {
+                    file_args(args, program, function, set)
+                        .with_context(|| format!("invalid argument set #{idx}"))
+                })
                 .collect()
+        }
+        _ => Ok(vec![program_args(args, program, function)?]),
+    }
+}
+
+fn program_args(args: &Args, program: &Program, function: &str) -> Result<Vec<Arg>> {
+    if let Some(input) = &args.scenario_arguments {
+        return file_args(args, program, function, input.clone());
+    }
+    if let Some(path) = &args.arguments_file {
+        let input: serde_json::Value = serde_json::from_str(&read_arguments_file(path)?)
+            .with_context(|| "deserializing arguments file failed")?;
+        return file_args(args, program, function, input);
+    }
+
+    let literals = parse_literals(&args.arguments)?;
+    if args.typed {
+        let values = literals.iter().map(Literal::to_json).collect();
+        arguments::typed_args(program, function, values)
+    } else {
+        arguments::literal_args(program, function, &literals)
+    }
+}
+
+/// Arguments from a file, either serialized felts or typed values.
+fn file_args(
+    args: &Args,
+    program: &Program,
+    function: &str,
+    input: serde_json::Value,
+) -> Result<Vec<Arg>> {
+    if args.typed {
+        let values: Vec<serde_json::Value> =
+            serde_json::from_value(input).with_context(|| "expected an array of arguments")?;
+        return arguments::typed_args(program, function, values);
+    }
+    let as_vec: Vec<BigUintAsHex> =
+        serde_json::from_value(input).with_context(|| "expected an array of felts")?;
+    Ok(as_vec
+        .into_iter()
+        .map(|v| Arg::Value(v.value.into()))
+        .collect())
+}
+
+/// Read the arguments file, `-` stands for stdin which is read only once.
+fn read_arguments_file(path: &Utf8PathBuf) -> Result<String> {
+    static STDIN: OnceLock<String> = OnceLock::new();
+    if path.as_str() != "-" {
+        return fs::read_to_string(path).with_context(|| "reading arguments file failed");
+    }
+    if let Some(input) = STDIN.get() {
+        return Ok(input.clone());
+    }
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .with_context(|| "reading arguments from stdin failed")?;
+    Ok(STDIN.get_or_init(|| input).clone())
+}
+
+/// Parse literals of all occurrences of a command line argument.
+fn parse_literals(values: &[String]) -> Result<Vec<Literal>> {
+    let mut literals = Vec::new();
+    for value in values {
+        literals.extend(arguments::parse_literals(value)?);
+    }
+    Ok(literals)
+}
+
+fn profile_program(args: &Args, program: Program) -> Result<Profiled> {
+    let runs = run_program(args, &program)?;
+    write_program(args, program, runs)
+}
+
+/// Runs of a program, computed in parallel and without printing anything, so that their outputs
+/// can be written in order afterwards.
+enum ProgramRuns {
+    CodeSize(Profiled),
+    /// Fuzzing prints the failed inputs as it goes, so it runs when the outputs are written.
+    Fuzz,
+    AllFunctions(Vec<(String, Result<FunctionRuns>)>),
+    Function(FunctionRuns),
+}
+
+/// Profiles of the argument sets of a function with their folded stacks.
+struct FunctionRuns {
+    runs: Vec<(String, Profile)>,
+    arguments_repr: String,
+}
+
+fn run_program(args: &Args, program: &Program) -> Result<ProgramRuns> {
+    if args.run.code_size {
+        return profiler::code_size(program).map(|folded| ProgramRuns::CodeSize(folded.into()));
+    }
+    if args.all_functions {
+        let names = profiler::entrypoint_names(program);
+        let runs = names
+            .par_iter()
+            .map(|name| run_function(args, program, name))
+            .collect::<Vec<_>>();
+        return Ok(ProgramRuns::AllFunctions(names.into_iter().zip(runs).collect()));
+    }
+    if args.fuzz.is_some() {
+        return Ok(ProgramRuns::Fuzz);
+    }
+    run_function(args, program, &args.function).map(ProgramRuns::Function)
+}
+
+/// Write the outputs of the runs of a program, in the order of the functions and argument sets.
+fn write_program(args: &Args, program: Program, runs: ProgramRuns) -> Result<Profiled> {
+    match runs {
+        ProgramRuns::CodeSize(profiled) => Ok(profiled),
+        ProgramRuns::Fuzz => {
+            let seed = args.fuzz_seed.unwrap_or_else(rand::random);
+            fuzz::fuzz(
+                &program,
+                &args.function,
+                args.fuzz.unwrap_or_default(),
+                seed,
+                &args.run.options()?,
+            )
+            .map(Profiled::from)
+        }
+        ProgramRuns::AllFunctions(functions) => write_all_functions(args, program, functions),
+        ProgramRuns::Function(runs) => write_function(args, &program, runs),
+    }
+}
+
+/// Run the function once per argument set, the sets run in parallel.
+fn run_function(args: &Args, program: &Program, function: &str) -> Result<FunctionRuns> {
+    let options = args.run.options()?;
+    let arg_sets = program_arg_sets(args, program, function)?;
+    let arguments_repr = format!("{arg_sets:?}");
+    let runs = arg_sets
+        .into_par_iter()
+        .enumerate()
+        .map(|(idx, program_args)| {
+            let profile = profiler::profile(program.clone(), function, program_args, &options)
+                .with_context(|| format!("failed to profile argument set #{idx}"))?;
+            Ok((profiler::folded(&profile, &options), profile))
+        })
+        .collect::<Result<_>>()?;
+    Ok(FunctionRuns {
+        runs,
+        arguments_repr,
+    })
+}
+
+/// Write the outputs of the argument sets in their order and merge the results, the profile of
+/// the function.
+fn write_function(args: &Args, program: &Program, runs: FunctionRuns) -> Result<Profiled> {
+    let options = args.run.options()?;
+    if args.output_per_set {
+        for (idx, (result, profile)) in runs.runs.iter().enumerate() {
+            let set_profiles = std::slice::from_ref(profile);
+            let set_result = Profiled::new(result.clone(), set_profiles)
+                .with_sample_types(set_profiles, &options)
+                .with_statements(program, set_profiles, &options, &args.output);
+            write_output(&set_result, &args.output.with_prefix(&format!("set{idx}")))?;
+        }
+    }
+    let (results, profiles): (Vec<_>, Vec<_>) = runs.runs.into_iter().unzip();
+    let result = folded::merge(results.iter().map(String::as_str));
+    write_reports(&args.report, &profiles, &result)?;
+    Ok(Profiled::new(result, &profiles)
+        .with_sample_types(&profiles, &options)
+        .with_statements(program, &profiles, &options, &args.output)
+        .with_hashes(program, Some(runs.arguments_repr)))
+}
+
+/// Write the outputs of the functions in their order and put each one under its own root frame.
+fn write_all_functions(
+    args: &Args,
+    program: Program,
+    functions: Vec<(String, Result<FunctionRuns>)>,
+) -> Result<Profiled> {
+    let mut result = Profiled::from(String::new());
+    for (name, runs) in functions {
+        match runs.and_then(|runs| write_function(args, &program, runs)) {
+            Ok(function_result) => {
+                for line in function_result.folded.lines() {
+                    result.folded.push_str(&format!("{name};{line}\n"));
+                }
+                for (builtin, count) in function_result.builtins {
+                    *result.builtins.entry(builtin).or_default() += count;
+                }
+                merge_sample_types(&mut result.sample_types, &name, function_result.sample_types);
+                if let Some(function_statements) = function_result.statements {
+                    match result.statements.as_mut() {
+                        Some(statements) => statements.merge(function_statements),
+                        None => result.statements = Some(function_statements),
+                    }
+                }
+            }
+            Err(err) => outln!("Skipping {name}: {err:#}"),
+        }
+    }
+    Ok(result.with_hashes(&program, None))
+}
+
+/// Put the sample types of a function under its own root frame.
+fn merge_sample_types(
+    sample_types: &mut Vec<(Weight, String)>,
+    name: &str,
+    function_sample_types: Vec<(Weight, String)>,
+) {
+    for (model, folded) in function_sample_types {
+        let idx = match sample_types.iter().position(|(existing, _)| *existing == model) {
+            Some(idx) => idx,
+            None => {
+                sample_types.push((model, String::new()));
+                sample_types.len() - 1
+            }
         };
+        for line in folded.lines() {
+            sample_types[idx].1.push_str(&format!("{name};{line}\n"));
+        }
+    }
+}
 
-        if !args.no_build {
-            let filter = PackagesFilter::generate_for::<Metadata>(vec![package.clone()].iter());
-            ScarbCommand::new()
-                .arg("build")
-                .env("SCARB_TARGET_KINDS", "lib")
-                .env("SCARB_PACKAGES_FILTER", filter.to_env())
-                .run()?;
+fn list_functions(args: &ListArgs) -> Result<()> {
+    let path = match &args.sierra_file {
+        Some(path) => path.clone(),
+        None => {
+            let metadata = scarb::metadata(&args.build)?;
+            let package = args.packages_filter.match_one(&metadata)?;
+            package_sierra_file(&metadata, &package, &args.build)?
         }
+    };
+    let program = load_program(&path)?;
+    for function in profiler::list_functions(&program) {
+        println!("{function}");
+    }
+    Ok(())
+}
+
+fn summarize(args: &SummaryArgs) -> Result<()> {
+    let profile = burnprofile::load(&args.profile_file)?;
+    let baseline = args.baseline.as_ref().map(burnprofile::load).transpose()?;
+    let summary = match args.format {
+        SummaryFormat::Markdown => {
+            markdown::render("Profile", &profile, baseline.as_deref(), args.top)
+        }
+    };
+    match &args.output_file {
+        Some(path) => fs::write(path, summary)
+            .with_context(|| format!("failed to write summary: {path}"))?,
+        None => print!("{summary}"),
+    }
+    Ok(())
+}
+
+fn trend(args: &TrendArgs) -> Result<()> {
+    let histories = history::load(args.name.as_deref())?;
+    let trend = match args.format {
+        TrendFormat::Table => history::table(&histories),
+        TrendFormat::Csv => history::csv(&histories),
+        TrendFormat::Json => serde_json::to_string_pretty(&histories)? + "\n",
+    };
+    match &args.output_file {
+        Some(path) => fs::write(path, trend)
+            .with_context(|| format!("failed to write trend: {path}"))?,
+        None => print!("{trend}"),
+    }
+    Ok(())
+}
+
+fn merge(args: &MergeArgs) -> Result<()> {
+    let profiles = args
+        .profile_files
+        .iter()
+        .map(burnprofile::load)
+        .collect::<Result<Vec<_>>>()?;
+    let merged = folded::merge(profiles.iter().map(String::as_str));
+    if burnprofile::is_burnprofile(&args.output_file) {
+        let profile = burnprofile::BurnProfile::new(&merged, None, None, None);
+        burnprofile::write(&profile, &args.output_file)?;
+    } else {
+        output::write(&args.output_file, merged)
+            .with_context(|| format!("failed to write merged profile: {}", args.output_file))?;
+    }
+    output::written("Merged profile", &args.output_file);
+    Ok(())
+}
+
+fn convert(args: &ConvertArgs) -> Result<()> {
+    let path = &args.profile_file;
+    let is_pprof = path.extension() == Some("pprof")
+        || path.as_str().ends_with(".pb.gz")
+        || path.as_str().ends_with(".pb");
+    let folded = if is_pprof {
+        pprof_profile::folded(&pprof_profile::read(path)?)
+    } else {
+        burnprofile::load(path)?
+    };
+    let output = OutputArgs {
+        output_type: args.to,
+        output_file: args.output_file.clone(),
+        open_in_browser: args.open_in_browser,
+        ..Default::default()
+    };
+    write_output_file(&folded.into(), &output)
+}
 
-        let filename = format!("{}.sierra.json", package.name);
-        let path = Utf8PathBuf::from(env::var("SCARB_TARGET_DIR")?)
-            .join(env::var("SCARB_PROFILE")?)
-            .join(filename.clone());
+fn diff(args: &DiffArgs) -> Result<()> {
+    let sources = (&args.git_ref, args.inputs.as_slice(), &args.base, &args.profile_file);
+    let (base, current) = match sources {
+        (Some(git_ref), _, _, _) => profile_ref(git_ref, &args.profile_args)?,
+        (None, [base, current], _, _) => profile_inputs(base, current, &args.profile_args)?,
+        (None, [], Some(base), Some(profile_file)) => {
+            ensure!(
+                args.profile_args.is_empty(),
+                "profile arguments are only used with --ref or --inputs"
+            );
+            (burnprofile::load(base)?, burnprofile::load(profile_file)?)
+        }
+        _ => bail!("either the profile files, --ref or --inputs have to be given"),
+    };
+    let (base, current) = if args.exact_names {
+        (base, current)
+    } else {
+        let rename = |folded: &str| folded::rename_frames(folded, profiler::logical_name);
+        (rename(&base), rename(&current))
+    };
+    let options = differential::Options {
+        normalize: args.normalize,
+        ..Default::default()
+    };
+    let mut folded = Vec::new();
+    differential::from_readers(options, base.as_bytes(), current.as_bytes(), &mut folded)
+        .with_context(|| "failed to compare profiles")?;
+
+    let folded = String::from_utf8(folded).with_context(|| "invalid differential profile")?;
+    from_lines(
+        &mut Options::default(),
+        folded.lines(),
+        output::create(&args.output_file)?,
+    )
+    .with_context(|| "failed to write differential flamegraph")?;
+    output::written("Differential flamegraph", &args.output_file);
+
+    if args.open_in_browser {
+        let absolute_path = fs::canonicalize(&args.output_file)?;
+        webbrowser::open(&format!("file://{}", absolute_path.display()))?;
+    }
 
+    let report = DeltaReport::new(&base, &current);
+    report.print(args.top);
+    if let Some(max_regression) = args.max_regression {
+        let change = report.total.percent();
         ensure!(
-            path.exists(),
-            format!(
-                r#"
-                Package has not been compiled, file does not exist: {filename}
-                make sure you have `[lib]` target in Scarb.toml
-            "#
-            )
+            change <= max_regression,
+            "total weight regressed by {change:.2}%, more than the allowed {max_regression}%"
         );
+    }
+    Ok(())
+}
 
-        let program = serde_json::from_str::<VersionedProgram>(
-            &fs::read_to_string(path.clone())
-                .with_context(|| format!("failed to read Sierra file: {path}"))?,
-        )
-        .with_context(|| format!("failed to deserialize Sierra program: {path}"))?;
+/// Profile the package at the git ref and in the working tree with the same arguments,
+/// returns the folded profiles of the ref and of the working tree.
+fn profile_ref(git_ref: &str, profile_args: &[String]) -> Result<(String, String)> {
+    in_temp_dir("diff", |dir| {
+        let worktree = git::Worktree::add(git_ref, dir.join("worktree"))?;
+        outln!("Profiling {git_ref}");
+        let base_file = dir.join("base.burnprofile");
+        let command = worktree_command(&worktree, profile_args, &base_file)?;
+        let base = run_burn(command, git_ref, &base_file)?;
+
+        outln!("Profiling the working tree");
+        let current_file = dir.join("current.burnprofile");
+        let command = burn_command(profile_args, &current_file)?;
+        let current = run_burn(command, "the working tree", &current_file)?;
+        Ok((base, current))
+    })
+}
+
+/// Profile the program with the base and the current arguments file and the same other
+/// arguments, returns the folded profiles of both runs.
+fn profile_inputs(
+    base: &Utf8PathBuf,
+    current: &Utf8PathBuf,
+    profile_args: &[String],
+) -> Result<(String, String)> {
+    in_temp_dir("inputs", |dir| {
+        let profile = |input: &Utf8PathBuf, path: Utf8PathBuf| {
+            outln!("Profiling with {input}");
+            let mut command = burn_command(profile_args, &path)?;
+            command.args(["--arguments-file", input.as_str()]);
+            run_burn(command, input.as_str(), &path)
+        };
+        let base = profile(base, dir.join("base.burnprofile"))?;
+        let current = profile(current, dir.join("current.burnprofile"))?;
+        Ok((base, current))
+    })
+}
+
+/// Find the first commit between the good and the bad ref whose total weight is above the
+/// threshold with `git bisect`, profiling every candidate with the same arguments.
+fn bisect(args: &BisectArgs) -> Result<()> {
+    in_temp_dir("bisect", |dir| {
+        let worktree = git::Worktree::add(&args.bad, dir.join("worktree"))?;
+        worktree.git(&["bisect", "start", args.bad.as_str(), args.good.as_str()])?;
+        let profile_file = dir.join("profile.burnprofile");
+        let result = loop {
+            let commit = worktree.git(&["log", "-1", "--format=%h %s"])?;
+            outln!("Profiling {commit}");
+            let command = worktree_command(&worktree, &args.profile_args, &profile_file)?;
+            let verdict = match run_burn(command, &commit, &profile_file) {
+                Ok(folded) => {
+                    let total_weight = folded::total_weight(&folded);
+                    outln!("Total weight {total_weight}");
+                    if total_weight > args.threshold {
+                        "bad"
+                    } else {
+                        "good"
+                    }
+                }
+                Err(err) => {
+                    outln!("{err:#}, skipping the commit");
+                    "skip"
+                }
+            };
+            let output = worktree.git(&["bisect", verdict])?;
+            if let Some(first_bad) = output
+                .lines()
+                .find_map(|line| line.strip_suffix(" is the first bad commit"))
+            {
+                break worktree.git(&["log", "-1", "--format=%h %s", first_bad]);
+            }
+            if output.contains("only 'skip'ped commits left") {
+                break Err(anyhow::anyhow!("{output}"));
+            }
+        };
+        let _ = worktree.git(&["bisect", "reset"]);
+        outln!("First commit above {}: {}", args.threshold, result?);
+        Ok(())
+    })
+}
+
+/// Run the function with a temporary directory, which is removed afterwards.
+fn in_temp_dir<T>(name: &str, f: impl FnOnce(&Utf8PathBuf) -> Result<T>) -> Result<T> {
+    let dir = Utf8PathBuf::try_from(std::env::temp_dir())?
+        .join(format!("scarb-burn-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {dir}"))?;
+    let result = f(&dir);
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Command profiling the package in the worktree like `burn_command` in the working tree,
+/// the worktree is built in its own target directory.
+fn worktree_command(
+    worktree: &git::Worktree,
+    profile_args: &[String],
+    path: &Utf8PathBuf,
+) -> Result<Command> {
+    let current_dir = Utf8PathBuf::try_from(std::env::current_dir()?)?;
+    let dir = worktree
+        .map(&current_dir)
+        .with_context(|| "the current directory is not in the git repository")?;
+    let mut command = burn_command(profile_args, path)?;
+    command.current_dir(dir).env_remove("SCARB_TARGET_DIR");
+    if let Ok(path) = std::env::var("SCARB_MANIFEST_PATH") {
+        command.env_remove("SCARB_MANIFEST_PATH");
+        if let Some(path) = worktree.map(path.as_ref()) {
+            command.env("SCARB_MANIFEST_PATH", path);
+        }
+    }
+    Ok(command)
+}
+
+/// Command running this executable with the arguments, writing a burnprofile to the path.
+fn burn_command(profile_args: &[String], path: &Utf8PathBuf) -> Result<Command> {
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .args(profile_args)
+        .args(["--output-type", "burnprofile", "--output-file", path.as_str()]);
+    Ok(command)
+}
+
+fn run_burn(mut command: Command, what: &str, path: &Utf8PathBuf) -> Result<String> {
+    let status = command
+        .status()
+        .with_context(|| format!("failed to profile {what}"))?;
+    ensure!(status.success(), "failed to profile {what}");
+    burnprofile::load(path)
+}
+
+fn profile_test(args: &TestArgs) -> Result<Profiled> {
+    let metadata = scarb::metadata(&args.build)?;
+    let package = args.packages_filter.match_one(&metadata)?;
+
+    scarb::build(&package, "test", &args.build)?;
+    let (path, test_name) = scarb::find_test(&metadata, &package, &args.filter)?;
+    eprintln!("Profiling test {test_name}");
+
+    let (program, locations) = load_artifact(&path)?;
+    if args.run.code_size {
+        return profiler::code_size(&program).map(Profiled::from);
+    }
+    let options = args.run.options()?;
+    let profile = profiler::profile(program.clone(), &test_name, vec![], &options)?;
+    let result = profiler::folded(&profile, &options);
+    let profiles = std::slice::from_ref(&profile);
+    write_reports(&args.report, profiles, &result)?;
+    Ok(Profiled::new(result, profiles)
+        .with_locations(locations)
+        .with_sample_types(profiles, &options)
+        .with_statements(&program, profiles, &options, &args.output)
+        .with_limits(Limits::from_manifest(&package, &test_name)?)
+        .with_package(Some(&package))
+        .with_hashes(&program, None))
+}
+
+fn profile_contract(args: &ContractArgs) -> Result<Profiled> {
+    let (contract_class, package) = match &args.contract_class {
+        Some(path) => (contract::load_contract_class(path)?, None),
+        None => {
+            let metadata = scarb::metadata(&args.build)?;
+            let package = args.packages_filter.match_one(&metadata)?;
 
-        let profiling_info = profiler::profile(program, program_args)?;
-        profiling_info.to_string()
+            scarb::build(&package, "starknet-contract", &args.build)?;
+            let path = scarb::contract_artifact(&metadata, &package, args.contract.as_deref())?;
+            (contract::load_contract_class(&path)?, Some(package))
+        }
+    };
+    let limits = match &package {
+        Some(package) => Limits::from_manifest(package, &args.entrypoint)?,
+        None => None,
     };
 
-    match args.output_type {
-        OutputType::Flamegraph => {
-            let mut opt = Options::default();
-            let file = fs::File::create(&args.output_file)
-                .with_context(|| "failed to create output file")?;
-            from_lines(&mut opt, result.lines(), file)
-                .with_context(|| "failed to write flamegraph")?;
+    if args.class_size || args.class_size_file.is_some() {
+        let report = ClassReport::new(&contract_class, args.l2_gas_price)?;
+        emit_report(
+            args.class_size,
+            args.class_size_file.as_ref(),
+            args.report.top,
+            || report,
+        )?;
+    }
+    if args.run.code_size {
+        return profiler::code_size(&contract::extract_program(&contract_class)?)
+            .map(Profiled::from);
+    }
+    let calldata = match (&args.from_tx, &args.rpc_url) {
+        (Some(tx_hash), Some(rpc_url)) => {
+            let calls = rpc::transaction_calls(rpc_url, tx_hash)?;
+            contract::entrypoint_calldata(calls, &args.entrypoint)?
+        }
+        _ if !args.named_args.is_empty() => {
+            let param_names = contract::param_names(&contract_class, &args.entrypoint)?;
+            arguments::serialized_args(&arguments::named_literals(&param_names, &args.named_args)?)
+        }
+        _ => arguments::serialized_args(&parse_literals(&args.calldata)?),
+    };
+    let calldata_len = calldata.len();
+    let calldata_repr = format!("{calldata:?}");
+    let options = args.run.options()?;
+    let profile = contract::profile(&contract_class, &args.entrypoint, calldata, &options)?;
+    let result = profiler::folded(&profile, &options);
+    write_reports(&args.report, std::slice::from_ref(&profile), &result)?;
+    emit_report(args.fee, args.fee_file.as_ref(), args.report.top, || {
+        FeeReport::new(
+            &profile,
+            calldata_len,
+            args.l2_gas_price,
+            args.l1_data_gas_price,
+        )
+    })?;
+    if let (Some(tx_hash), Some(rpc_url)) = (&args.verify_against_tx, &args.rpc_url) {
+        let onchain = rpc::execution_resources(rpc_url, tx_hash)?;
+        let report = VerifyReport::new(&profile, calldata_len, &onchain);
+        emit_report(true, args.verify_file.as_ref(), args.report.top, || report)?;
+    }
+    let profiles = std::slice::from_ref(&profile);
+    let program = contract::extract_program(&contract_class)?;
+    Ok(Profiled::new(result, profiles)
+        .with_sample_types(profiles, &options)
+        .with_statements(&program, profiles, &options, &args.output)
+        .with_limits(limits)
+        .with_package(package.as_ref())
+        .with_hashes(&program, Some(calldata_repr)))
+}
 
-            println!("Flamegraph written to {}", args.output_file);
+/// Number of top functions in the job summary.
+const SUMMARY_TOP: usize = 10;
 
-            if args.open_in_browser {
-                let absolute_path = fs::canonicalize(&args.output_file)?;
-                let url = format!("file://{}", absolute_path.display());
-                webbrowser::open(&url)?;
+/// Weight models encoded as separate sample types of pprof profiles.
+const SAMPLE_TYPES: [Weight; 4] = [
+    Weight::Steps,
+    Weight::SierraGas,
+    Weight::Builtins,
+    Weight::Memory,
+];
+
+/// Folded profile of the runs along with the builtin usages counted by the VM
+/// and the source locations of the functions.
+struct Profiled {
+    folded: String,
+    builtins: BTreeMap<String, usize>,
+    locations: SourceMap,
+    /// Profile weighted by the pprof sample types, the selected weight model goes first.
+    sample_types: Vec<(Weight, String)>,
+    /// Weights of the Sierra statements, only collected for the annotated listings.
+    statements: Option<StatementWeights>,
+    /// Limits from the package manifest, checked after writing the outputs.
+    limits: Option<Limits>,
+    /// Hashes of the profiled program and its arguments, see `burnprofile::hash`.
+    program_hash: Option<String>,
+    arguments_hash: Option<String>,
+    /// Return values and resources of the runs.
+    runs: Vec<RunSummary>,
+    /// Name and version of the profiled package, if any.
+    package: Option<(String, String)>,
+    /// Function the runs profiled, `None` if there were none or several ones.
+    entrypoint: Option<String>,
+    /// Seconds since the Unix epoch when the profile was made.
+    timestamp: u64,
+}
+
+impl Profiled {
+    fn new(folded: String, profiles: &[Profile]) -> Self {
+        let mut builtins = BTreeMap::new();
+        for profile in profiles {
+            for (builtin, count) in &profile.resources.basic_resources.builtin_instance_counter {
+                *builtins.entry(builtin.to_string()).or_default() += count;
             }
         }
-        OutputType::Pprof => {
-            write_pprof(result.lines(), &args.output_file)?;
-            println!("Profile file written to {}", args.output_file);
-
-            if args.open_in_browser {
-                Command::new("go")
-                    .args([
-                        "tool",
-                        "pprof",
-                        "-http=:8000",
-                        &args.output_file.to_string(),
-                    ])
-                    .status()
-                    .with_context(|| "failed to start pprof server")?;
+        Self {
+            folded,
+            builtins,
+            locations: SourceMap::default(),
+            sample_types: Vec::new(),
+            statements: None,
+            limits: None,
+            program_hash: None,
+            arguments_hash: None,
+            runs: profiles.iter().map(RunSummary::new).collect(),
+            package: None,
+            entrypoint: match profiles {
+                [first, rest @ ..] if rest.iter().all(|p| p.entrypoint == first.entrypoint) => {
+                    Some(first.entrypoint.clone())
+                }
+                _ => None,
+            },
+            timestamp: history::now(),
+        }
+    }
+
+    /// Collect the weights of the statements of the profiled program if an output needs them.
+    fn with_statements(
+        self,
+        program: &Program,
+        profiles: &[Profile],
+        options: &ProfileOptions,
+        output: &OutputArgs,
+    ) -> Self {
+        if !output.uses_statements() {
+            return self;
+        }
+        Self {
+            statements: Some(StatementWeights::new(program.clone(), profiles, options)),
+            ..self
+        }
+    }
+
+    /// Weigh the profiles also by the pprof sample types other than the selected weight model,
+    /// which costs are not overridden by the cost table as it is in the units of the model.
+    fn with_sample_types(self, profiles: &[Profile], options: &ProfileOptions) -> Self {
+        let mut sample_types = vec![(options.weight, self.folded.clone())];
+        for model in SAMPLE_TYPES {
+            if model == options.weight {
+                continue;
             }
+            let options = ProfileOptions {
+                weight: model,
+                cost_table: None,
+                weight_model: None,
+                min_weight: 1,
+                ..options.clone()
+            };
+            let results: Vec<_> = profiles
+                .iter()
+                .map(|profile| profiler::folded(profile, &options))
+                .collect();
+            sample_types.push((model, folded::merge(results.iter().map(String::as_str))));
+        }
+        Self {
+            sample_types,
+            ..self
+        }
+    }
+
+    fn with_locations(self, locations: SourceMap) -> Self {
+        Self { locations, ..self }
+    }
+
+    fn with_limits(self, limits: Option<Limits>) -> Self {
+        Self { limits, ..self }
+    }
+
+    fn with_package(self, package: Option<&PackageMetadata>) -> Self {
+        Self {
+            package: package.map(|package| (package.name.clone(), package.version.to_string())),
+            ..self
+        }
+    }
+
+    /// Identify the program and the arguments it was run with, e.g. by their `Debug` form.
+    fn with_hashes(self, program: &Program, arguments: Option<String>) -> Self {
+        Self {
+            program_hash: Some(burnprofile::hash(&program.to_string())),
+            arguments_hash: arguments.as_deref().map(burnprofile::hash),
+            ..self
+        }
+    }
+
+    /// Name of the weight model of the stacks, `None` for profiles read from a file.
+    fn weight(&self) -> Option<String> {
+        self.sample_types.first().and_then(|(model, _)| {
+            Some(model.to_possible_value()?.get_name().to_string())
+        })
+    }
+
+    fn sink_profile(&self) -> SinkProfile<'_> {
+        let sample_types = self
+            .sample_types
+            .iter()
+            .map(|(model, folded)| {
+                let (name, unit) = pprof_profile::sample_type(*model);
+                SampleType { name, unit, folded }
+            })
+            .collect();
+        SinkProfile {
+            folded: &self.folded,
+            weight: self.weight(),
+            sample_types,
+            builtins: self.builtins.clone(),
+            locations: Some(&self.locations),
+            statements: self.statements.as_ref(),
+            program_hash: self.program_hash.clone(),
+            arguments_hash: self.arguments_hash.clone(),
+            runs: &self.runs,
+            metadata: self.metadata(),
+        }
+    }
+
+    /// Name the baseline of the profile is saved under: the profiled function, which tells
+    /// apart the entrypoints of a package, or the title if it isn't known.
+    fn key(&self, title: &str) -> String {
+        self.entrypoint.clone().unwrap_or_else(|| title.to_string())
+    }
+
+    /// Origin of the profile, `None` for profiles not produced by running.
+    fn metadata(&self) -> Option<RunMetadata> {
+        if self.runs.is_empty() {
+            return None;
         }
+        let (package, version) = self.package.clone().unzip();
+        Some(RunMetadata {
+            package,
+            version,
+            commit: git::head(),
+            program_hash: self.program_hash.clone(),
+            arguments_hash: self.arguments_hash.clone(),
+            weight: self.weight(),
+            timestamp: self.timestamp,
+        })
     }
 
+    /// Totals named like the limits: the weights of the sample types and the builtin usages.
+    fn totals(&self) -> BTreeMap<String, usize> {
+        let weights = self.sample_types.iter().filter_map(|(model, folded)| {
+            let name = model.to_possible_value()?.get_name().to_string();
+            Some((name, folded::total_weight(folded)))
+        });
+        let builtins = self
+            .builtins
+            .iter()
+            .map(|(builtin, count)| (limits::builtin_name(builtin), *count));
+        weights.chain(builtins).collect()
+    }
+}
+
+/// Profiles read from a file or not produced by running have no builtin usages.
+impl From<String> for Profiled {
+    fn from(folded: String) -> Self {
+        Self::new(folded, &[])
+    }
+}
+
+/// Print how a run ended if it didn't return: the panic with its traces or the bound it
+/// stopped at.
+fn print_stop(profile: &Profile) {
+    if let Some(reason) = &profile.stop_reason {
+        eprintln!("Warning: {reason}, the profile ends where it stopped");
+    }
+    if let Some(msg) = &profile.panic_message {
+        eprintln!("Panicked with [{msg}]");
+        for trace in &profile.panic_traces {
+            eprintln!("{}", profiler::render_trace(trace));
+        }
+    }
+}
+
+/// Print and export the reports requested on the command line.
+fn write_reports(args: &ReportArgs, profiles: &[Profile], result: &str) -> Result<()> {
+    profiles.iter().for_each(print_stop);
+    emit_report(args.builtins, args.builtins_file.as_ref(), args.top, || {
+        aggregate(profiles, BuiltinReport::add)
+    })?;
+    emit_report(args.memory, args.memory_file.as_ref(), args.top, || {
+        aggregate(profiles, MemoryReport::add)
+    })?;
+    emit_report(args.libfuncs, args.libfuncs_file.as_ref(), args.top, || {
+        aggregate(profiles, LibfuncReport::add)
+    })?;
+    emit_report(args.syscalls, args.syscalls_file.as_ref(), args.top, || {
+        aggregate(profiles, SyscallReport::add)
+    })?;
+    emit_report(args.storage, args.storage_file.as_ref(), args.top, || {
+        aggregate(profiles, StorageReport::add)
+    })?;
+    emit_report(args.events, args.events_file.as_ref(), args.top, || {
+        aggregate(profiles, EventReport::add)
+    })?;
+    emit_report(args.counts, args.counts_file.as_ref(), args.top, || {
+        CallReport::new(profiles, result)
+    })?;
+    emit_report(args.loops, args.loops_file.as_ref(), args.top, || {
+        LoopReport::new(profiles, result)
+    })?;
+    emit_report(args.categories, args.categories_file.as_ref(), args.top, || {
+        CategoryReport::new(result)
+    })?;
+    if let Some(top_paths) = args.top_paths {
+        emit_report(true, None, top_paths, || PathReport::new(result))?;
+    }
+    emit_report(
+        args.functions,
+        args.functions_file.as_ref(),
+        args.top,
+        || FunctionReport::new(result),
+    )?;
+    emit_report(args.report, None, args.top, || {
+        HotspotReport(FunctionReport::new(result))
+    })
+}
+
+/// Build a report by adding the runs one by one.
+fn aggregate<R: Default>(profiles: &[Profile], add: fn(&mut R, &Profile)) -> R {
+    let mut report = R::default();
+    profiles
+        .iter()
+        .for_each(|profile| add(&mut report, profile));
+    report
+}
+
+/// Print and export a report, it is only built if requested.
+fn emit_report<R: Report>(
+    print: bool,
+    file: Option<&Utf8PathBuf>,
+    top: usize,
+    build: impl FnOnce() -> R,
+) -> Result<()> {
+    if !print && file.is_none() {
+        return Ok(());
+    }
+    let report = build();
+    if print {
+        report.print(top);
+    }
+    if let Some(path) = file {
+        report.write(path)?;
+    }
     Ok(())
 }
 
-fn write_pprof<'a, I>(lines: I, output_path: &Utf8PathBuf) -> Result<()>
-where
-    I: Iterator<Item = &'a str>,
-{
-    let mut data: HashMap<Frames, isize> = HashMap::new();
-    for line in lines {
-        let (stack, count_str) = line
-            .rsplit_once(' ')
-            .ok_or_else(|| anyhow::anyhow!("invalid line format: {line}"))?;
-
-        let frames: Vec<Vec<Symbol>> = stack
-            .split(';')
-            .rev()
-            .map(|name| {
-                let symbol = Symbol {
-                    name: Some(name.as_bytes().to_vec()),
-                    filename: None,
-                    lineno: None,
-                    addr: None,
-                };
-                vec![symbol]
+/// Build the package unless disabled and locate its Sierra artifact.
+fn package_sierra_file(
+    metadata: &Metadata,
+    package: &PackageMetadata,
+    build_args: &BuildArgs,
+) -> Result<Utf8PathBuf> {
+    let (target_kind, path) = scarb::sierra_artifact(metadata, package)?;
+    scarb::build(package, &target_kind, build_args)?;
+    scarb::ensure_compiled(&path, &target_kind)?;
+    Ok(path)
+}
+
+/// Output file of a single package or argument set, e.g. `pkg.flamegraph.svg`.
+fn prefixed_output_file(output_file: &Utf8PathBuf, prefix: &str) -> Utf8PathBuf {
+    let file_name = output_file.file_name().unwrap_or_default();
+    output_file.with_file_name(format!("{prefix}.{file_name}"))
+}
+
+fn load_program(path: &Utf8PathBuf) -> Result<Program> {
+    Ok(load_artifact(path)?.0)
+}
+
+/// Load the Sierra program along with the source locations from its debug info.
+fn load_artifact(path: &Utf8PathBuf) -> Result<(Program, SourceMap)> {
+    let program = serde_json::from_str::<VersionedProgram>(
+        &fs::read_to_string(path).with_context(|| format!("failed to read Sierra file: {path}"))?,
+    )
+    .with_context(|| format!("failed to deserialize Sierra program: {path}"))?;
+    let artifact = program
+        .into_v1()
+        .with_context(|| "failed to convert to v1")?;
+    let locations = source::source_map(&artifact.program, artifact.debug_info.as_ref());
+    Ok((artifact.program, locations))
+}
+
+/// Write the outputs and the job summary of a profiled program, compare it with its baseline
+/// and check its limits.
+fn write_results(profiled: &Profiled, output: &OutputArgs, title: &str) -> Result<()> {
+    for (idx, run) in profiled.runs.iter().enumerate() {
+        if profiled.runs.len() > 1 {
+            eprintln!("Run {}: {run}", idx + 1);
+        } else {
+            eprintln!("{run}");
+        }
+    }
+    write_output(profiled, output)?;
+    // The profile of a panicked run is written to see where it blew up, but not recorded
+    if let Some(msg) = profiled.runs.iter().find_map(|run| run.panic_message.as_ref()) {
+        bail!("panicked with [{msg}], the profile ends where it panicked");
+    }
+    let key = profiled.key(title);
+    let weight = profiled.weight();
+    let saved = if output.save_baseline {
+        let path = baseline::save(&key, &profiled.folded, weight.clone())?;
+        eprintln!("Baseline saved to {path}");
+        None
+    } else {
+        baseline::load(&key, weight.as_deref())?
+    };
+    if let Some(saved) = &saved {
+        let unit = profiled
+            .sample_types
+            .first()
+            .map_or("weight", |(model, _)| pprof_profile::sample_type(*model).0);
+        let change = baseline::change(saved, &profiled.folded, unit);
+        eprintln!("{change} relative to the baseline {}", baseline::path(&key));
+    }
+    if output.record_history {
+        let mut weights: BTreeMap<String, usize> = profiled
+            .sample_types
+            .iter()
+            .map(|(model, folded)| {
+                let (name, _) = pprof_profile::sample_type(*model);
+                (name.to_string(), folded::total_weight(folded))
             })
             .collect();
-        let count: isize = count_str
-            .parse()
-            .context(format!("failed to parse sample count: `{}`", line))?;
-
-        let frame = Frames {
-            frames,
-            thread_name: "main".into(),
-            thread_id: 0,
-            sample_timestamp: SystemTime::now(),
+        if weights.is_empty() {
+            weights.insert("weight".into(), folded::total_weight(&profiled.folded));
+        }
+        let record = history::Record::new(weights, profiled.builtins.clone());
+        // Totals of different weight models don't make a series
+        let name = match &weight {
+            Some(weight) => format!("{key}@{weight}"),
+            None => key.clone(),
+        };
+        let path = history::append(&name, &record)?;
+        eprintln!("Run recorded in {path}");
+    }
+    if output.gha_summary {
+        // An explicit baseline takes precedence over the saved one
+        let baseline = match &output.summary_baseline {
+            Some(path) => Some(burnprofile::load(path)?),
+            None => saved,
         };
-        data.insert(frame, count);
+        write_job_summary(profiled, title, baseline.as_deref())?;
+    }
+    if let Some(limits) = &profiled.limits {
+        limits.check(&profiled.totals())?;
     }
+    Ok(())
+}
 
-    let report = Report {
-        data,
-        timing: Default::default(),
-    };
-    let profile = report.pprof()?;
-    let file =
-        fs::File::create(output_path).with_context(|| "failed to create pprof output file")?;
-    let mut encoder = GzEncoder::new(file, Compression::default());
-    profile
-        .write_to_writer(&mut encoder)
-        .with_context(|| "failed to write pprof data")?;
-    encoder.finish()?;
+/// Append the summary of the profile to the job summary of GitHub Actions.
+fn write_job_summary(profiled: &Profiled, title: &str, baseline: Option<&str>) -> Result<()> {
+    let path = std::env::var("GITHUB_STEP_SUMMARY").with_context(|| {
+        "GITHUB_STEP_SUMMARY is not set, --gha-summary works in GitHub Actions"
+    })?;
+    let summary = markdown::render(
+        &format!("Profile of `{title}`"),
+        &profiled.folded,
+        baseline,
+        SUMMARY_TOP,
+    );
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("failed to open job summary at {path}"))?;
+    writeln!(file, "{summary}").with_context(|| "failed to write job summary")?;
+    Ok(())
+}
 
+fn write_output(profiled: &Profiled, output: &OutputArgs) -> Result<()> {
+    if output.outputs.is_empty() {
+        return write_output_file(profiled, output);
+    }
+    for (output_type, path) in &output.outputs {
+        let mut output = output.clone();
+        output.output_type = *output_type;
+        output.output_file = Some(path.clone());
+        write_output_file(profiled, &output)?;
+    }
     Ok(())
 }
+
+fn write_output_file(profiled: &Profiled, output: &OutputArgs) -> Result<()> {
+    let output_file = output.output_file();
+    ensure!(
+        !output.open_in_browser || !output::is_stdout(&output_file),
+        "--open-in-browser requires an output file"
+    );
+    let profile = profiled.sink_profile();
+    sink::write(output.sink().as_ref(), &profile, &output_file, output.open_in_browser)
+}
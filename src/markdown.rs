@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::report::FunctionReport;
+
+/// Render the total weight and the top functions by inclusive weight of a folded profile
+/// as GitHub-flavored Markdown, with changes relative to the baseline profile if given.
+pub fn render(title: &str, profile: &str, baseline: Option<&str>, top: usize) -> String {
+    let current = FunctionReport::new(profile);
+    let baseline = baseline.map(FunctionReport::new);
+    let mut output = format!("### {title}\n\n");
+
+    match &baseline {
+        Some(baseline) => {
+            output.push_str("| | Baseline | Current | Change |\n|---|---:|---:|---:|\n");
+            let _ = writeln!(
+                output,
+                "| Total weight | {} | {} | {} |",
+                baseline.total,
+                current.total,
+                change(Some(baseline.total), current.total)
+            );
+        }
+        None => {
+            output.push_str("| | Current |\n|---|---:|\n");
+            let _ = writeln!(output, "| Total weight | {} |", current.total);
+        }
+    }
+
+    let mut functions: Vec<_> = current.functions.iter().collect();
+    functions.sort_by(|a, b| b.1.inclusive.cmp(&a.1.inclusive).then(a.0.cmp(b.0)));
+    functions.truncate(top);
+    let _ = write!(output, "\n#### Top {top} functions\n\n");
+    output.push_str("| Function | Inclusive | % | Exclusive |");
+    output.push_str(match &baseline {
+        Some(_) => " Change |\n|---|---:|---:|---:|---:|\n",
+        None => "\n|---|---:|---:|---:|\n",
+    });
+    let baseline_weights: Option<BTreeMap<&str, usize>> = baseline.as_ref().map(|baseline| {
+        baseline
+            .functions
+            .iter()
+            .map(|(name, weights)| (name.as_str(), weights.inclusive))
+            .collect()
+    });
+    for (name, weights) in functions {
+        let percent = 100.0 * weights.inclusive as f64 / current.total.max(1) as f64;
+        let _ = write!(
+            output,
+            "| `{}` | {} | {percent:.2}% | {} |",
+            name.replace('|', "\\|").replace('`', "'"),
+            weights.inclusive,
+            weights.exclusive
+        );
+        if let Some(baseline_weights) = &baseline_weights {
+            let previous = baseline_weights.get(name.as_str()).copied();
+            let _ = write!(output, " {} |", change(previous, weights.inclusive));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// Relative change of a weight, `new` if it did not exist in the baseline.
+fn change(previous: Option<usize>, current: usize) -> String {
+    match previous {
+        None => "new".to_string(),
+        Some(previous) if previous == current => "0.00%".to_string(),
+        Some(0) => "+∞".to_string(),
+        Some(previous) => {
+            let percent = 100.0 * (current as f64 - previous as f64) / previous as f64;
+            format!("{percent:+.2}%")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let baseline = "main;foo;store_temp 100\nmain;return 10";
+        let current = "main;foo;store_temp 120\nmain;bar;jump 5\nmain;return 10";
+        let markdown = render("Profile", current, Some(baseline), 10);
+        assert!(markdown.starts_with("### Profile\n"));
+        assert!(markdown.contains("| Total weight | 110 | 135 | +22.73% |"));
+        assert!(markdown.contains("| `foo` | 120 | 88.89% | 120 | +20.00% |"));
+        assert!(markdown.contains("| `bar` | 5 | 3.70% | 5 | new |"));
+        assert!(!render("Profile", current, None, 10).contains("Change"));
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Output path standing for the standard output.
+const STDOUT: &str = "-";
+
+/// Whether an output goes to the standard output, set before anything is printed.
+static STDOUT_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Print a line of a report or a status line like `println!`, on the standard error when an
+/// output goes to the standard output so that the line doesn't corrupt it.
+#[macro_export]
+macro_rules! outln {
+    () => {
+        $crate::output::print_line(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::print_line(format_args!($($arg)*))
+    };
+}
+
+pub fn is_stdout(path: &Utf8PathBuf) -> bool {
+    path.as_str() == STDOUT
+}
+
+/// Name of a profile, e.g. of a package or an entrypoint, safe to be used as a file name.
+pub fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Send the lines printed with [`outln`] to the standard error if the output is `-`. Outputs
+/// are claimed before the run, as reports are printed before the outputs are written.
+pub fn claim(path: &Utf8PathBuf) {
+    if is_stdout(path) {
+        STDOUT_TAKEN.store(true, Ordering::Relaxed);
+    }
+}
+
+pub fn print_line(args: fmt::Arguments) {
+    if STDOUT_TAKEN.load(Ordering::Relaxed) {
+        eprintln!("{args}");
+    } else {
+        println!("{args}");
+    }
+}
+
+/// Open the output file or the standard output for `-`.
+pub fn create(path: &Utf8PathBuf) -> Result<Box<dyn Write>> {
+    if is_stdout(path) {
+        claim(path);
+        return Ok(Box::new(io::stdout().lock()));
+    }
+    let file =
+        fs::File::create(path).with_context(|| format!("failed to create output file: {path}"))?;
+    Ok(Box::new(io::BufWriter::new(file)))
+}
+
+/// Write the contents to the output file or the standard output for `-`.
+pub fn write(path: &Utf8PathBuf, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut writer = create(path)?;
+    writer.write_all(contents.as_ref())?;
+    Ok(writer.flush()?)
+}
+
+/// Report a written output, on the standard error when the output itself went to stdout.
+pub fn written(what: &str, path: &Utf8PathBuf) {
+    if is_stdout(path) {
+        eprintln!("{what} written to stdout");
+    } else {
+        print_line(format_args!("{what} written to {path}"));
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use pprof::protos::{self, Message};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Read, Write};
+
+use crate::folded;
+use crate::output;
+use crate::profiler::Weight;
+use crate::source::Location;
+
+/// Weights of the stacks of a single pprof sample type.
+pub struct SampleType<'a> {
+    pub name: &'a str,
+    pub unit: &'a str,
+    pub folded: &'a str,
+}
+
+/// Name and unit of the sample type of the given weight model.
+pub fn sample_type(model: Weight) -> (&'static str, &'static str) {
+    match model {
+        Weight::Steps => ("steps", "count"),
+        Weight::SierraGas => ("sierra_gas", "gas"),
+        Weight::L2Gas => ("l2_gas", "gas"),
+        Weight::Prover => ("prover", "cells"),
+        Weight::Memory => ("memory", "cells"),
+        Weight::Builtins => ("builtins", "count"),
+    }
+}
+
+const MAPPING_ID: u64 = 1;
+
+/// Strings of a profile referenced by their index, the first one has to be empty.
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, i64>,
+}
+
+impl StringTable {
+    fn new() -> Self {
+        Self {
+            strings: vec![String::new()],
+            indices: HashMap::from([(String::new(), 0)]),
+        }
+    }
+
+    fn index(&mut self, string: &str) -> i64 {
+        if let Some(index) = self.indices.get(string) {
+            return *index;
+        }
+        let index = self.strings.len() as i64;
+        self.strings.push(string.to_string());
+        self.indices.insert(string.to_string(), index);
+        index
+    }
+}
+
+/// Build a pprof profile with a value per sample type for every stack, the first sample
+/// type is the default one. Stacks missing from a sample type weigh zero in it.
+pub fn profile(
+    sample_types: &[SampleType],
+    locations: &HashMap<String, Location>,
+    comments: &[String],
+) -> protos::Profile {
+    let mut values: BTreeMap<&str, Vec<i64>> = BTreeMap::new();
+    for (idx, sample_type) in sample_types.iter().enumerate() {
+        for (stack, weight) in folded::lines(sample_type.folded) {
+            values
+                .entry(stack)
+                .or_insert_with(|| vec![0; sample_types.len()])[idx] += weight as i64;
+        }
+    }
+
+    let mut strings = StringTable::new();
+    let mut profile = protos::Profile::default();
+    profile.sample_type = sample_types
+        .iter()
+        .map(|sample_type| protos::ValueType {
+            ty: strings.index(sample_type.name),
+            unit: strings.index(sample_type.unit),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>()
+        .into();
+
+    // There are no addresses, so every frame is a function with a single location
+    // sharing its id, all in a single mapping of the program
+    let mut ids = HashMap::new();
+    let mut samples = Vec::new();
+    for (stack, value) in values {
+        let location_ids = stack
+            .split(';')
+            .rev()
+            .map(|name| {
+                *ids.entry(name).or_insert_with(|| {
+                    let id = profile.function.len() as u64 + 1;
+                    let location = locations.get(name);
+                    let line = location.map_or(0, |location| location.line as i64);
+                    profile.function.push(protos::Function {
+                        id,
+                        name: strings.index(name),
+                        system_name: strings.index(name),
+                        filename: location.map_or(0, |location| strings.index(&location.file)),
+                        start_line: line,
+                        ..Default::default()
+                    });
+                    profile.location.push(protos::Location {
+                        id,
+                        mapping_id: MAPPING_ID,
+                        line: vec![protos::Line {
+                            function_id: id,
+                            line,
+                            ..Default::default()
+                        }]
+                        .into(),
+                        ..Default::default()
+                    });
+                    id
+                })
+            })
+            .collect();
+        samples.push(protos::Sample {
+            location_id: location_ids,
+            value,
+            ..Default::default()
+        });
+    }
+    profile.sample = samples.into();
+    profile.mapping = vec![protos::Mapping {
+        id: MAPPING_ID,
+        filename: strings.index("sierra"),
+        has_functions: true,
+        has_filenames: !locations.is_empty(),
+        has_line_numbers: !locations.is_empty(),
+        ..Default::default()
+    }]
+    .into();
+    profile.comment = comments
+        .iter()
+        .map(|comment| strings.index(comment))
+        .collect::<Vec<_>>()
+        .into();
+    profile.string_table = strings.strings.into();
+    profile
+}
+
+/// Write the gzipped pprof profile, the comments are shown by `pprof -comments`.
+pub fn write(
+    sample_types: &[SampleType],
+    locations: &HashMap<String, Location>,
+    comments: &[String],
+    path: &Utf8PathBuf,
+) -> Result<()> {
+    let mut encoder = GzEncoder::new(output::create(path)?, Compression::default());
+    profile(sample_types, locations, comments)
+        .write_to_writer(&mut encoder)
+        .with_context(|| format!("failed to write pprof profile: {path}"))?;
+    encoder.finish()?.flush()?;
+    Ok(())
+}
+
+/// Read a pprof profile, gzipped or not.
+pub fn read(path: &Utf8PathBuf) -> Result<protos::Profile> {
+    let mut bytes =
+        fs::read(path).with_context(|| format!("failed to read pprof profile: {path}"))?;
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decoded)
+            .with_context(|| format!("failed to decompress pprof profile: {path}"))?;
+        bytes = decoded;
+    }
+    protos::Profile::parse_from_bytes(&bytes)
+        .with_context(|| format!("invalid pprof profile: {path}"))
+}
+
+/// Folded stacks of the first sample type of a pprof profile, the frames of the inlined
+/// functions of a location go before the frame of the function they were inlined into.
+pub fn folded(profile: &protos::Profile) -> String {
+    let string = |idx: i64| profile.string_table.get(idx as usize).map_or("", String::as_str);
+    let functions: HashMap<u64, &str> = profile
+        .function
+        .iter()
+        .map(|function| (function.id, string(function.name)))
+        .collect();
+    let locations: HashMap<u64, Vec<&str>> = profile
+        .location
+        .iter()
+        .map(|location| {
+            let frames = location
+                .line
+                .iter()
+                .rev()
+                .filter_map(|line| functions.get(&line.function_id).copied())
+                .collect();
+            (location.id, frames)
+        })
+        .collect();
+
+    let mut stacks = Vec::new();
+    for sample in profile.sample.iter() {
+        let Some(weight) = sample.value.first().filter(|weight| **weight > 0) else {
+            continue;
+        };
+        let frames: Vec<&str> = sample
+            .location_id
+            .iter()
+            .rev()
+            .flat_map(|id| locations.get(id).into_iter().flatten().copied())
+            .collect();
+        if !frames.is_empty() {
+            stacks.push(format!("{} {weight}", frames.join(";")));
+        }
+    }
+    folded::merge([stacks.join("\n").as_str()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let sample_types = [
+            SampleType {
+                name: "steps",
+                unit: "count",
+                folded: "main;foo 10\nmain;bar 5",
+            },
+            SampleType {
+                name: "builtins",
+                unit: "count",
+                folded: "main;foo 2\nmain;baz 1",
+            },
+        ];
+        let profile = profile(&sample_types, &HashMap::new(), &["weight: steps".into()]);
+        assert_eq!(profile.sample_type.len(), 2);
+        assert_eq!(profile.string_table[0], "");
+        let values: Vec<_> = profile.sample.iter().map(|s| s.value.clone()).collect();
+        // Stacks are sorted: main;bar, main;baz, main;foo
+        assert_eq!(values, [vec![5, 0], vec![0, 1], vec![10, 2]]);
+        // main, bar, baz and foo
+        assert_eq!(profile.function.len(), 4);
+        assert_eq!(profile.location.len(), 4);
+        assert_eq!(profile.sample[0].location_id[1], profile.sample[2].location_id[1]);
+        let leaf = profile.sample[2].location_id[0];
+        let line = &profile.location[leaf as usize - 1].line[0];
+        let name = profile.function[line.function_id as usize - 1].name;
+        assert_eq!(profile.string_table[name as usize], "foo");
+        assert_eq!(profile.string_table[profile.comment[0] as usize], "weight: steps");
+        assert_eq!(folded(&profile), "main;bar 5\nmain;foo 10\n");
+    }
+}
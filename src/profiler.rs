@@ -1,26 +1,421 @@
-use anyhow::{bail, Context};
+use anyhow::{bail, ensure, Context};
 use cairo_lang_runner::profiling::{
     ProcessedProfilingInfo, ProfilingInfoProcessor, ProfilingInfoProcessorParams,
 };
 use cairo_lang_runner::short_string::as_cairo_short_string;
 use cairo_lang_runner::{
-    Arg, ProfilingInfoCollectionConfig, RunResultValue, SierraCasmRunner, StarknetState,
+    Arg, ProfilingInfoCollectionConfig, RunResultStarknet, RunResultValue, SierraCasmRunner,
+    StarknetExecutionResources, StarknetState,
 };
-use cairo_lang_sierra::program::VersionedProgram;
-use cairo_lang_utils::ordered_hash_map::OrderedHashMap;
+use cairo_lang_sierra::ids::ConcreteTypeId;
+use cairo_lang_sierra::program::{Function, Program, Statement, VersionedProgram};
+use cairo_lang_sierra_to_casm::compiler::{compile, CairoProgram, SierraToCasmConfig};
+use cairo_lang_sierra_to_casm::metadata::calc_metadata;
+use camino::Utf8PathBuf;
+use clap::ValueEnum;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::sync::Arc;
 
-/// Load Sierra program from source, run it and generate a profile.
+use crate::folded;
+use crate::state::StateFixture;
+pub use crate::folded::{logical_name, strip_generics};
+
+/// Prefix of the wrapper functions generated for `#[executable]` entrypoints.
+const EXECUTABLE_PREFIX: &str = "__executable_wrapper__";
+
+/// Builtins and implicits which are not passed by the user.
+pub const IMPLICIT_TYPES: &[&str] = &[
+    "RangeCheck",
+    "RangeCheck96",
+    "Bitwise",
+    "Pedersen",
+    "Poseidon",
+    "EcOp",
+    "AddMod",
+    "MulMod",
+    "GasBuiltin",
+    "SegmentArena",
+    "System",
+];
+
+/// Breakdown of the profile supported by the profiling info processor.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dimension {
+    /// Call stacks down to the libfuncs, loops and recursion collapsed.
+    ScopedStatement,
+    /// Sierra statement indices.
+    Statement,
+    /// Concrete libfuncs, e.g. `store_temp<felt252>`.
+    ConcreteLibfunc,
+    /// Generic libfuncs, e.g. `store_temp`.
+    GenericLibfunc,
+    /// Sierra functions, including corelib ones.
+    UserFunction,
+    /// Call stacks of Sierra functions.
+    StackTrace,
+}
+
+impl Dimension {
+    fn name(self) -> &'static str {
+        match self {
+            Dimension::ScopedStatement => "scoped-statement",
+            Dimension::Statement => "statement",
+            Dimension::ConcreteLibfunc => "concrete-libfunc",
+            Dimension::GenericLibfunc => "generic-libfunc",
+            Dimension::UserFunction => "user-function",
+            Dimension::StackTrace => "stack-trace",
+        }
+    }
+}
+
+/// Granularity the function frames are collapsed to, libfunc leaves are merged into the callers.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    /// First path segment, e.g. `core`.
+    Crate,
+    /// Path without the function name, e.g. `core::array`.
+    Module,
+    /// Full function path.
+    Function,
+}
+
+impl GroupBy {
+    fn frame(self, function: &str) -> String {
+        let segments = path_segments(function);
+        match self {
+            GroupBy::Crate => segments[0].to_string(),
+            GroupBy::Module if segments.len() > 1 => segments[..segments.len() - 1].join("::"),
+            GroupBy::Module | GroupBy::Function => function.to_string(),
+        }
+    }
+}
+
+/// Split a path on `::` outside of generic arguments, e.g. `core::array::ArrayImpl::<felt252>`.
+fn path_segments(path: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    let bytes = path.as_bytes();
+    for (idx, byte) in bytes.iter().enumerate() {
+        match byte {
+            b'<' => depth += 1,
+            b'>' => depth = depth.saturating_sub(1),
+            b':' if depth == 0 && idx > start && bytes.get(idx + 1) == Some(&b':') => {
+                segments.push(&path[start..idx]);
+                start = idx + 2;
+            }
+            _ => {}
+        }
+    }
+    segments.push(&path[start..]);
+    segments
+}
+
+/// Built-in weight models, the meaning of the profile weights.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Weight {
+    /// CASM steps, builtins are counted as their step-equivalent in Sierra gas.
+    #[default]
+    Steps,
+    /// Sierra gas, as metered by `withdraw_gas`.
+    SierraGas,
+    /// Starknet L2 gas, builtins are priced according to the fee model.
+    L2Gas,
+    /// Approximate STARK proving cost in trace cells of a Stwo-like Cairo AIR.
+    Prover,
+    /// Approximate memory cells, a cell per step plus the builtin segment cells.
+    Memory,
+    /// Builtin usages estimated from the libfuncs, steps are not counted.
+    Builtins,
+}
+
+impl Weight {
+    /// Weight of a frame which executed the given number of steps,
+    /// builtins are only accounted when the libfunc of the frame is known.
+    /// Costs from the table take precedence over the ones of the model.
+    pub fn weight(
+        self,
+        libfunc: Option<&str>,
+        steps: usize,
+        cost_table: Option<&CostTable>,
+    ) -> usize {
+        if let Some(cost) = libfunc.and_then(|libfunc| cost_table?.libfunc_cost(libfunc)) {
+            return steps * cost;
+        }
+        let step_gas = cost_table
+            .and_then(|table| table.step)
+            .unwrap_or(self.builtin_pricing().step_cost());
+        let builtin_gas: usize = libfunc
+            .map(|libfunc| builtin_usages(libfunc, steps))
+            .into_iter()
+            .flatten()
+            .map(|(builtin, usages)| {
+                let gas = cost_table
+                    .and_then(|table| table.builtins.get(builtin.name()).copied())
+                    .unwrap_or_else(|| builtin.gas(self.builtin_pricing()));
+                gas * usages
+            })
+            .sum();
+        let gas = steps * step_gas + builtin_gas;
+        match self {
+            Weight::Steps => gas / step_gas.max(1),
+            Weight::SierraGas
+            | Weight::L2Gas
+            | Weight::Prover
+            | Weight::Memory
+            | Weight::Builtins => gas,
+        }
+    }
+
+    fn step_cost(self) -> usize {
+        match self {
+            Weight::Prover => STEP_CELLS,
+            Weight::Memory => 1,
+            Weight::Builtins => 0,
+            _ => STEP_GAS,
+        }
+    }
+
+    /// Weight of syscalls costing the given Sierra gas. Syscalls are executed by the OS
+    /// outside of the program trace, so they carry no weight in the prover and memory models.
+    fn syscall_weight(
+        self,
+        name: &str,
+        calls: usize,
+        cost_table: Option<&CostTable>,
+    ) -> Option<usize> {
+        if let Some(cost) = cost_table.and_then(|table| table.syscalls.get(name)) {
+            return Some(calls * cost);
+        }
+        let gas = calls * syscall_gas(name);
+        match self {
+            Weight::Steps => Some(gas / STEP_GAS),
+            Weight::SierraGas | Weight::L2Gas => Some(gas),
+            Weight::Prover | Weight::Memory | Weight::Builtins => None,
+        }
+    }
+
+    /// Steps are converted to step-equivalents via Sierra gas.
+    fn builtin_pricing(self) -> Weight {
+        match self {
+            Weight::Steps => Weight::SierraGas,
+            model => model,
+        }
+    }
+}
+
+/// User-defined costs overriding the ones of the weight model, in the model units.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CostTable {
+    /// Cost of a step.
+    pub step: Option<usize>,
+    /// Cost of a single usage per builtin name, e.g. `pedersen`.
+    #[serde(default)]
+    pub builtins: HashMap<String, usize>,
+    /// Cost per step of a libfunc, by concrete or generic name, replaces step and builtin costs.
+    #[serde(default)]
+    pub libfuncs: HashMap<String, usize>,
+    /// Cost of a single call per syscall name, e.g. `storage_read`.
+    #[serde(default)]
+    pub syscalls: HashMap<String, usize>,
+}
+
+impl CostTable {
+    pub fn load(path: &Utf8PathBuf) -> anyhow::Result<Self> {
+        toml::from_str(
+            &fs::read_to_string(path)
+                .with_context(|| format!("failed to read cost table: {path}"))?,
+        )
+        .with_context(|| format!("failed to parse cost table: {path}"))
+    }
+
+    fn libfunc_cost(&self, libfunc: &str) -> Option<usize> {
+        let generic = libfunc.split('<').next().unwrap_or(libfunc);
+        self.libfuncs
+            .get(libfunc)
+            .or_else(|| self.libfuncs.get(generic))
+            .copied()
+    }
+}
+
+/// Weight model of the frames, one of the built-in [`Weight`]s or a custom one plugged in,
+/// e.g. to research new cost models.
+pub trait WeightModel: Debug + Send + Sync {
+    /// Name of the weights, e.g. `steps`.
+    fn name(&self) -> String;
+
+    /// The built-in weight it is, which the cost table overrides, `None` for custom models.
+    fn builtin(&self) -> Option<Weight> {
+        None
+    }
+
+    /// Weight of a frame which executed the given number of steps, the libfunc of the frame is
+    /// known for the statements of the scoped stacks, the listings and the libfunc breakdowns.
+    fn weight(&self, libfunc: Option<&str>, steps: usize) -> usize;
+
+    /// Weight of the calls of a syscall, `None` if syscalls are not weighted.
+    fn syscall_weight(&self, _name: &str, _calls: usize) -> Option<usize> {
+        None
+    }
+}
+
+impl WeightModel for Weight {
+    fn name(&self) -> String {
+        self.to_possible_value()
+            .map_or_else(|| format!("{self:?}"), |value| value.get_name().to_string())
+    }
+
+    fn builtin(&self) -> Option<Weight> {
+        Some(*self)
+    }
+
+    fn weight(&self, libfunc: Option<&str>, steps: usize) -> usize {
+        Weight::weight(*self, libfunc, steps, None)
+    }
+
+    fn syscall_weight(&self, name: &str, calls: usize) -> Option<usize> {
+        Weight::syscall_weight(*self, name, calls, None)
+    }
+}
+
+/// Settings of a profiling run.
+#[derive(Clone, Debug, Default)]
+pub struct ProfileOptions {
+    /// Gas budget of gas-enabled programs, unlimited if not set.
+    pub available_gas: Option<usize>,
+    /// Bound on the steps, gas-enabled programs are stopped by a gas budget refined to the
+    /// steps the runs take while the others fail once they return.
+    pub max_steps: Option<usize>,
+    /// Breakdowns to collect, scoped statements if empty.
+    pub dimensions: Vec<Dimension>,
+    /// Meaning of the weights in the rendered profile.
+    pub weight: Weight,
+    /// Costs overriding the ones of the weight model.
+    pub cost_table: Option<CostTable>,
+    /// Custom model replacing the built-in weight and the cost table.
+    pub weight_model: Option<Arc<dyn WeightModel>>,
+    /// State the program runs against, empty if not set.
+    pub starknet_state: Option<StateFixture>,
+    /// Attribute dict squashing to the functions accessing dicts.
+    pub attribute_dict_squash: bool,
+    /// Merge the instantiations of generic functions and libfuncs.
+    pub collapse_generics: bool,
+    /// Collapse the function frames to a coarser granularity.
+    pub group_by: Option<GroupBy>,
+    /// Frames with a lower weight are pruned, in steps when processing
+    /// and in the units of the weight model when rendering.
+    pub min_weight: usize,
+}
+
+impl ProfileOptions {
+    fn dimensions(&self) -> &[Dimension] {
+        if self.dimensions.is_empty() {
+            &[Dimension::ScopedStatement]
+        } else {
+            &self.dimensions
+        }
+    }
+
+    /// Weight of a frame according to the cost model, or the weight model and the cost table.
+    pub fn frame_weight(&self, libfunc: Option<&str>, steps: usize) -> usize {
+        match &self.weight_model {
+            Some(model) => model.weight(libfunc, steps),
+            None => self.weight.weight(libfunc, steps, self.cost_table.as_ref()),
+        }
+    }
+
+    fn syscall_weight(&self, name: &str, calls: usize) -> Option<usize> {
+        match &self.weight_model {
+            Some(model) => model.syscall_weight(name, calls),
+            None => self
+                .weight
+                .syscall_weight(name, calls, self.cost_table.as_ref()),
+        }
+    }
+}
+
+/// Profile of a single run along with the resources it used.
+pub struct Profile {
+    /// Sierra id of the profiled function, e.g. `hello::main`.
+    pub entrypoint: String,
+    pub info: ProcessedProfilingInfo,
+    pub resources: StarknetExecutionResources,
+    /// Number of memory cells written by the run.
+    pub memory_cells: usize,
+    /// Size of the relocated memory, including holes.
+    pub memory_size: usize,
+    /// Felts returned by the function, the panic data if it ran out of gas.
+    pub return_values: Vec<String>,
+    pub panicked: bool,
+    /// Panic data decoded as short strings where possible if the run panicked, `None` if it
+    /// returned or ran out of the gas it was given.
+    pub panic_message: Option<String>,
+    /// Gas left by gas-enabled programs.
+    pub remaining_gas: Option<usize>,
+    /// Events in the order they were emitted.
+    pub events: Vec<EmittedEvent>,
+    /// Call stacks leading to the panic, from the entrypoint down to the panic function, empty
+    /// unless the run panicked unexpectedly.
+    pub panic_traces: Vec<Vec<String>>,
+    /// Bound the run stopped at before returning, e.g. the gas it was given, the profile ends
+    /// where it stopped.
+    pub stop_reason: Option<String>,
+}
+
+/// Event emitted by a run, read from the request of its `emit_event` syscall.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmittedEvent {
+    /// First key, the selector of the event name for the events deriving `starknet::Event`.
+    pub selector: Option<BigUint>,
+    pub keys: usize,
+    pub data: usize,
+}
+
+/// Run the given function of a Sierra program and generate a profile.
 pub fn profile(
-    program: VersionedProgram,
+    sierra_program: Program,
+    function: &str,
     program_args: Vec<Arg>,
-) -> anyhow::Result<ProcessedProfilingInfo> {
-    let sierra_program = program
-        .into_v1()
-        .with_context(|| "failed to convert to v1")?;
-    let gas_enabled = sierra_program.program.requires_gas_counter();
+    options: &ProfileOptions,
+) -> anyhow::Result<Profile> {
+    let entrypoint = find_function(&sierra_program, function)
+        .with_context(|| {
+            format!(
+                r#"
+            Make sure you have the following in Scarb.toml:
+
+            [cairo]
+            sierra-replace-ids = true
 
+            Error"#
+            )
+        })?
+        .clone();
+
+    // Executable wrappers take serialized arguments and an output array
+    let function_args = if is_executable_wrapper(&entrypoint) {
+        vec![Arg::Array(program_args), Arg::Array(vec![])]
+    } else {
+        program_args
+    };
+
+    profile_function(sierra_program, &entrypoint, function_args, options)
+}
+
+/// Run a function of the Sierra program with the exact arguments and generate a profile.
+pub fn profile_function(
+    sierra_program: Program,
+    entrypoint: &Function,
+    function_args: Vec<Arg>,
+    options: &ProfileOptions,
+) -> anyhow::Result<Profile> {
+    let gas_enabled = sierra_program.requires_gas_counter();
     let runner = SierraCasmRunner::new(
-        sierra_program.program.clone(),
+        sierra_program.clone(),
         if gas_enabled {
             Some(Default::default())
         } else {
@@ -34,91 +429,981 @@ pub fn profile(
     )
     .with_context(|| "failed to create SierraCasmRunner")?;
 
-    let entrypoint = runner.find_function("main").with_context(|| {
-        format!(
-            r#"
-            Make sure you have the following in Scarb.toml:
-
-            [cairo]
-            sierra-replace-ids = true
-
-            Error"#
-        )
-    })?;
-
-    let result = runner
-        .run_function_with_starknet_context(
-            entrypoint,
-            vec![Arg::Array(program_args), Arg::Array(vec![])],
-            if gas_enabled { Some(usize::MAX) } else { None },
-            StarknetState::default(),
-        )
-        .with_context(|| "failed to run the function")?;
+    let run = |available_gas: Option<usize>| {
+        let state = match &options.starknet_state {
+            Some(fixture) => fixture.starknet_state()?,
+            None => StarknetState::default(),
+        };
+        runner
+            .run_function_with_starknet_context(
+                entrypoint,
+                function_args.clone(),
+                available_gas,
+                state,
+            )
+            .with_context(|| "failed to run the function")
+    };
+    let steps = |result: &RunResultStarknet| result.used_resources.basic_resources.n_steps;
+    let gas_limit = options.available_gas.unwrap_or(usize::MAX);
+    // The runner has no step limit, a gas budget is the only way to stop a run
+    ensure!(
+        options.max_steps.is_none() || gas_enabled,
+        "the max steps can't be enforced on a program without gas, as the runner can only stop \
+        a run when it runs out of gas"
+    );
+    let (result, available_gas) = match options.max_steps {
+        Some(max_steps) => {
+            let (result, budget) =
+                run_within_steps(|budget| run(Some(budget)), steps, gas_limit, max_steps)?;
+            (result, Some(budget))
+        }
+        None if gas_enabled => (run(Some(gas_limit))?, options.available_gas),
+        None => (run(None)?, None),
+    };
 
-    if let RunResultValue::Panic(values) = result.value {
-        let msg = values
-            .iter()
-            .map(|v| as_cairo_short_string(v).unwrap_or_else(|| v.to_string()))
-            .collect::<Vec<_>>()
-            .join(", ");
-        bail!("panicked with [{msg}]")
+    // A bounded run which panicked ran out of its budget if it gets further with more gas
+    let mut stop_reason = None;
+    if let (RunResultValue::Panic(_), Some(budget)) = (&result.value, available_gas) {
+        let more_gas = if budget < gas_limit {
+            budget.saturating_mul(2).min(gas_limit)
+        } else {
+            budget.saturating_mul(2)
+        };
+        if budget < more_gas && steps(&run(Some(more_gas))?) > steps(&result) {
+            stop_reason = Some(if budget < gas_limit {
+                format!(
+                    "execution reached the step limit (max steps {}) after {} steps",
+                    options.max_steps.unwrap_or_default(),
+                    steps(&result)
+                )
+            } else {
+                format!("execution ran out of gas (available gas {budget})")
+            });
+        }
     }
+    let panic_message = match &result.value {
+        RunResultValue::Panic(values) if stop_reason.is_none() => Some(
+            values
+                .iter()
+                .map(|v| as_cairo_short_string(v).unwrap_or_else(|| v.to_string()))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        _ => None,
+    };
 
+    let enabled = |dimension| options.dimensions().contains(&dimension);
     let profiling_processor = ProfilingInfoProcessor::new(
         None,
-        sierra_program.program,
+        sierra_program,
         Default::default(),
         ProfilingInfoProcessorParams {
-            min_weight: 1,
-            process_by_statement: false,
-            process_by_concrete_libfunc: false,
-            process_by_generic_libfunc: false,
-            process_by_user_function: false,
+            min_weight: options.min_weight.max(1),
+            // Statement weights are also used by the annotated listings
+            process_by_statement: true,
+            process_by_concrete_libfunc: enabled(Dimension::ConcreteLibfunc),
+            process_by_generic_libfunc: enabled(Dimension::GenericLibfunc),
+            process_by_user_function: enabled(Dimension::UserFunction),
             process_by_original_user_function: false,
             process_by_cairo_function: false,
-            process_by_stack_trace: false,
+            process_by_stack_trace: enabled(Dimension::StackTrace),
             process_by_cairo_stack_trace: false,
-            process_by_scoped_statement: true,
+            process_by_scoped_statement: enabled(Dimension::ScopedStatement),
         },
     );
-    let mut processed_profiling_info =
-        profiling_processor.process(result.profiling_info.as_ref().unwrap());
+    let (values, panicked) = match &result.value {
+        RunResultValue::Success(values) => (values, false),
+        RunResultValue::Panic(values) => (values, true),
+    };
+    let mut profile = Profile {
+        entrypoint: entrypoint.id.to_string(),
+        info: profiling_processor.process(result.profiling_info.as_ref().unwrap()),
+        resources: result.used_resources,
+        memory_cells: result.memory.iter().filter(|cell| cell.is_some()).count(),
+        memory_size: result.memory.len(),
+        return_values: values.iter().map(ToString::to_string).collect(),
+        panicked,
+        panic_message,
+        remaining_gas: result
+            .gas_counter
+            .and_then(|gas| gas.to_string().parse().ok()),
+        events: emitted_events(&result.memory),
+        panic_traces: Vec::new(),
+        stop_reason,
+    };
+    if profile.panic_message.is_some() {
+        profile.panic_traces = panic_traces(&profile);
+    }
+    Ok(profile)
+}
+
+/// Rounds of refining the gas budget which enforces the max steps.
+const STEP_BUDGET_ROUNDS: usize = 5;
+
+/// Run with the gas budget stopping the run the closest to the max steps without exceeding
+/// them, along with the budget. Statements are charged ahead and builtins cost extra gas, so
+/// the budget of a step of gas per step is scaled by the steps the runs actually took.
+fn run_within_steps<R>(
+    run: impl Fn(usize) -> anyhow::Result<R>,
+    steps: impl Fn(&R) -> usize,
+    gas_limit: usize,
+    max_steps: usize,
+) -> anyhow::Result<(R, usize)> {
+    let mut budget = gas_limit.min(max_steps.saturating_mul(STEP_GAS));
+    // The largest budget within the steps with its run, and the smallest one exceeding them
+    let mut within: Option<(R, usize, usize)> = None;
+    let mut exceeding: Option<(R, usize)> = None;
+    for _ in 0..STEP_BUDGET_ROUNDS {
+        let result = run(budget)?;
+        let taken = steps(&result);
+        if taken <= max_steps {
+            // Nothing to gain if the run returned or used up all the gas there is
+            let done = taken == max_steps
+                || budget == gas_limit
+                || within.as_ref().is_some_and(|(_, _, steps)| *steps == taken);
+            within = Some((result, budget, taken));
+            if done {
+                break;
+            }
+        } else {
+            exceeding = Some((result, budget));
+        }
+        let low = within.as_ref().map_or(0, |(_, budget, _)| budget + 1);
+        let high = exceeding
+            .as_ref()
+            .map_or(gas_limit, |(_, budget)| budget.saturating_sub(1));
+        if low > high {
+            break;
+        }
+        let scaled = budget as u128 * max_steps as u128 / taken.max(1) as u128;
+        budget = usize::try_from(scaled).unwrap_or(usize::MAX).clamp(low, high);
+    }
+    match (within, exceeding) {
+        (Some((result, budget, _)), _) | (None, Some((result, budget))) => Ok((result, budget)),
+        (None, None) => bail!("no run within the max steps"),
+    }
+}
+
+/// Selector of the `emit_event` syscall, the short string `'EmitEvent'`.
+const EMIT_EVENT_SELECTOR: u128 = 0x456d69744576656e74;
+
+/// Events found in the relocated memory of a run by the requests of their syscalls: the
+/// selector, the gas and the start and end addresses of the keys and of the data.
+fn emitted_events<F>(memory: &[Option<F>]) -> Vec<EmittedEvent>
+where
+    F: PartialEq + From<u128> + ToString,
+{
+    let selector = Some(F::from(EMIT_EVENT_SELECTOR));
+    let value = |idx: usize| -> Option<BigUint> {
+        memory.get(idx)?.as_ref()?.to_string().parse().ok()
+    };
+    let address = |idx: usize| value(idx).and_then(|value| usize::try_from(&value).ok());
+    memory
+        .iter()
+        .enumerate()
+        .filter(|(_, cell)| **cell == selector)
+        .filter_map(|(idx, _)| {
+            let (keys_start, keys_end) = (address(idx + 2)?, address(idx + 3)?);
+            let (data_start, data_end) = (address(idx + 4)?, address(idx + 5)?);
+            // Other cells holding the same felt are unlikely to be followed by two spans
+            let spans = keys_start <= keys_end && data_start <= data_end;
+            (spans && keys_end.max(data_end) <= memory.len()).then(|| EmittedEvent {
+                selector: (keys_start < keys_end).then(|| value(keys_start)).flatten(),
+                keys: keys_end - keys_start,
+                data: data_end - data_start,
+            })
+        })
+        .collect()
+}
 
-    // Adjust weights according to the builtins/libfuncs table
-    if let Some(scoped_sierra_statement_weights) = processed_profiling_info
+/// Call stacks leading to the panic functions of the run, from the entrypoint down to the
+/// panic function, each once. Empty if the breakdowns with call stacks weren't collected.
+pub fn panic_traces(profile: &Profile) -> Vec<Vec<String>> {
+    let info = &profile.info;
+    let stacks = info
         .scoped_sierra_statement_weights
-        .as_mut()
-    {
-        adjust_weights(scoped_sierra_statement_weights);
+        .iter()
+        .flat_map(|weights| weights.keys())
+        .chain(info.stack_trace_weights.iter().flat_map(|weights| weights.keys()));
+    let mut traces: Vec<Vec<String>> = Vec::new();
+    for stack in stacks {
+        let Some(end) = stack
+            .iter()
+            .position(|frame| PANIC_FUNCTIONS.contains(&strip_generics(frame).as_str()))
+        else {
+            continue;
+        };
+        let trace = stack[..=end].to_vec();
+        if !traces.contains(&trace) {
+            traces.push(trace);
+        }
     }
+    traces
+}
 
-    Ok(processed_profiling_info)
+/// Trace with the innermost frame first, as in a backtrace.
+pub fn render_trace(trace: &[String]) -> String {
+    trace
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(idx, frame)| format!("  {idx:>2}: {frame}"))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-fn adjust_weights(weights: &mut OrderedHashMap<Vec<String>, usize>) {
-    weights.iter_mut().for_each(|(_k, _v)| {
-        //println!("{}: {}", k.join(" -> "), v);
+/// Render the collected breakdowns in the folded stacks format, flat ones as single frames.
+/// With several dimensions each one is put under its own root frame.
+/// Syscalls are rendered as `<caller>;syscall::<name>` frames next to their libfuncs.
+pub fn folded(profile: &Profile, options: &ProfileOptions) -> String {
+    let mut lines = Vec::new();
+    for_each_stack(profile, options, |stack, weight| {
+        lines.push(format!("{stack} {weight}"));
     });
+    lines.join("\n")
+}
+
+/// Call `f` with the stacks of [`folded`] and their weights as they are rendered, so that a
+/// profile can be consumed without collecting its stacks. Only the stacks of the dimensions
+/// which are post-processed, e.g. grouped, are collected before being passed on.
+pub fn for_each_stack(
+    profile: &Profile,
+    options: &ProfileOptions,
+    mut f: impl FnMut(&str, usize),
+) {
+    let info = &profile.info;
+    let dimensions = options.dimensions();
+    for &dimension in dimensions {
+        // Steps are converted according to the weight model, builtins are accounted
+        // for the frames where the libfunc is known
+        let weight = |libfunc: Option<&str>, steps: usize| options.frame_weight(libfunc, steps);
+        let prefix = if dimensions.len() > 1 {
+            format!("{};", dimension.name())
+        } else {
+            String::new()
+        };
+        let mut emit = |stack: &str, weight: usize| {
+            if weight >= options.min_weight {
+                f(&format!("{prefix}{stack}"), weight);
+            }
+        };
+        let buffered = dimension == Dimension::StackTrace
+            || (dimension == Dimension::ScopedStatement && options.attribute_dict_squash)
+            || options.collapse_generics
+            || options.group_by.is_some();
+        let mut dimension_lines = Vec::new();
+        let mut push = |frames: String, weight: usize| {
+            if buffered {
+                dimension_lines.push(format!("{frames} {weight}"));
+            } else {
+                emit(&frames, weight);
+            }
+        };
+        match dimension {
+            Dimension::ScopedStatement => {
+                for (stack, steps) in info
+                    .scoped_sierra_statement_weights
+                    .iter()
+                    .flat_map(|w| w.iter())
+                {
+                    push(
+                        panic_stack(profile, stack),
+                        weight(stack.last().map(String::as_str), *steps),
+                    );
+                }
+                for (stack, name, calls) in syscall_calls(profile) {
+                    if let Some(cost) = options.syscall_weight(&name, calls) {
+                        let caller = &stack[..stack.len() - 1];
+                        push(format!("{};syscall::{name}", caller.join(";")), cost);
+                    }
+                }
+            }
+            Dimension::Statement => {
+                for (idx, steps) in info.sierra_statement_weights.iter().flat_map(|w| w.iter()) {
+                    push(format!("#{}", idx.0), weight(None, *steps));
+                }
+            }
+            Dimension::ConcreteLibfunc | Dimension::GenericLibfunc => {
+                let Some(libfunc_weights) = &info.libfunc_weights else {
+                    continue;
+                };
+                let weights = if dimension == Dimension::ConcreteLibfunc {
+                    &libfunc_weights.concrete_libfunc_weights
+                } else {
+                    &libfunc_weights.generic_libfunc_weights
+                };
+                for (libfunc, steps) in weights.iter().flat_map(|w| w.iter()) {
+                    push(libfunc.to_string(), weight(Some(libfunc.as_str()), *steps));
+                }
+                if let Some(steps) = libfunc_weights.return_weight {
+                    push("return".to_string(), weight(None, steps));
+                }
+            }
+            Dimension::UserFunction => {
+                let weights = info
+                    .user_function_weights
+                    .as_ref()
+                    .and_then(|weights| weights.user_function_weights.as_ref());
+                for (function, steps) in weights.into_iter().flat_map(|w| w.iter()) {
+                    push(function.to_string(), weight(None, *steps));
+                }
+            }
+            Dimension::StackTrace => {
+                for (stack, steps) in info.stack_trace_weights.iter().flat_map(|w| w.iter()) {
+                    push(panic_stack(profile, stack), weight(None, *steps));
+                }
+            }
+        }
+        if !buffered {
+            continue;
+        }
+
+        let mut folded = dimension_lines.join("\n");
+        if dimension == Dimension::ScopedStatement && options.attribute_dict_squash {
+            folded = folded::attribute_dict_squash(&folded);
+        }
+        if dimension == Dimension::StackTrace {
+            folded = folded::collapse_recursion(&folded);
+        }
+        if options.collapse_generics {
+            folded = folded::rename_frames(&folded, strip_generics);
+        }
+        if let Some(group_by) = options.group_by {
+            // Scoped stacks end with a libfunc, the other stacks are made of functions only
+            let frame = |function: &str| group_by.frame(function);
+            folded = match dimension {
+                Dimension::ScopedStatement => folded::collapse(&folded, true, frame),
+                Dimension::UserFunction | Dimension::StackTrace => {
+                    folded::collapse(&folded, false, frame)
+                }
+                _ => folded,
+            };
+        }
+        for (stack, weight) in folded::lines(&folded) {
+            emit(stack, weight);
+        }
+    }
+}
+
+/// Corelib functions raising panics, which only run when the program panics.
+const PANIC_FUNCTIONS: [&str; 4] = [
+    "core::panic_with_felt252",
+    "core::panic_with_const_felt252",
+    "core::panics::panic",
+    "core::panics::panic_with_byte_array",
+];
+
+/// Stack joined into frames, the frames of the panic functions of a panicked run are marked
+/// with the panic data, e.g. `core::panic_with_felt252 [panicked with 'invalid amount']`.
+fn panic_stack(profile: &Profile, stack: &[String]) -> String {
+    let Some(msg) = &profile.panic_message else {
+        return stack.join(";");
+    };
+    stack
+        .iter()
+        .map(|frame| {
+            if PANIC_FUNCTIONS.contains(&strip_generics(frame).as_str()) {
+                format!("{frame} [panicked with {}]", msg.replace(';', ","))
+            } else {
+                frame.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Sierra gas of a syscall itself, excluding the execution of the called contract,
+/// after the Starknet versioned constants.
+pub fn syscall_gas(name: &str) -> usize {
+    const SYSCALL_BASE_GAS: usize = 100 * STEP_GAS;
+    const ENTRY_POINT_GAS: usize = 600 * STEP_GAS;
+    match name {
+        "call_contract" | "library_call" => 10 * SYSCALL_BASE_GAS + ENTRY_POINT_GAS,
+        "deploy" => 200 * SYSCALL_BASE_GAS + ENTRY_POINT_GAS,
+        "get_block_hash" => 50 * SYSCALL_BASE_GAS,
+        "keccak" => SYSCALL_BASE_GAS + 180_000,
+        _ => SYSCALL_BASE_GAS,
+    }
+}
+
+/// Syscall counts of the run by snake case name, e.g. `storage_read` for `StorageRead`.
+pub fn syscall_counts(resources: &StarknetExecutionResources) -> Vec<(String, usize)> {
+    let mut counts: Vec<_> = resources
+        .syscalls
+        .iter()
+        .map(|(selector, count)| (snake_case(selector), *count))
+        .collect();
+    counts.sort();
+    counts
+}
+
+/// Calls per syscall and stack of its libfunc, e.g. `storage_read_syscall`. The counts of the
+/// run are split proportionally to the steps of the stacks, as a syscall libfunc executes the
+/// same steps on each call.
+pub fn syscall_calls(profile: &Profile) -> Vec<(&[String], String, usize)> {
+    let stacks: Vec<(&[String], &str, usize)> = profile
+        .info
+        .scoped_sierra_statement_weights
+        .iter()
+        .flat_map(|weights| weights.iter())
+        .filter_map(|(stack, steps)| {
+            let name = stack.last()?.strip_suffix("_syscall")?;
+            Some((stack.as_slice(), name, *steps))
+        })
+        .collect();
+    let mut calls = Vec::new();
+    for (name, count) in syscall_counts(&profile.resources) {
+        let stacks: Vec<_> = stacks.iter().filter(|(_, n, _)| *n == name).collect();
+        let total_steps: usize = stacks.iter().map(|(_, _, steps)| steps).sum();
+        let mut remaining = count;
+        for (idx, (stack, _, steps)) in stacks.iter().enumerate() {
+            // The last stack takes the rounding remainder
+            let share = if idx + 1 == stacks.len() {
+                remaining
+            } else {
+                count * steps / total_steps.max(1)
+            };
+            remaining -= share;
+            calls.push((*stack, name.clone(), share));
+        }
+    }
+    calls
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (idx, c) in name.char_indices() {
+        if c.is_ascii_uppercase() {
+            if idx > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// Compile the program to CASM without a bytecode size limit.
+pub fn compile_casm(program: &Program) -> anyhow::Result<CairoProgram> {
+    let metadata = calc_metadata(program, Default::default())
+        .with_context(|| "failed to compute the metadata of the program")?;
+    compile(
+        program,
+        &metadata,
+        SierraToCasmConfig {
+            gas_usage_check: true,
+            max_bytecode_size: usize::MAX,
+        },
+    )
+    .with_context(|| "failed to compile the program to CASM")
+}
+
+/// Compile the program to CASM and render the bytecode size of every libfunc invocation
+/// in the folded format, under the path segments of the function containing it.
+pub fn code_size(program: &Program) -> anyhow::Result<String> {
+    let casm_program = compile_casm(program)?;
+
+    // Functions are contiguous ranges of statements starting at their entry points
+    let mut functions: Vec<_> = program
+        .funcs
+        .iter()
+        .map(|func| (func.entry_point.0, func.id.to_string()))
+        .collect();
+    functions.sort();
+    let mut lines = Vec::new();
+    let statements = program.statements.iter();
+    for (idx, (statement, info)) in statements
+        .zip(&casm_program.debug_info.sierra_statement_info)
+        .enumerate()
+    {
+        let function = match functions.partition_point(|(entry, _)| *entry <= idx) {
+            0 => continue,
+            pos => &functions[pos - 1].1,
+        };
+        let libfunc = match statement {
+            Statement::Invocation(invocation) => invocation.libfunc_id.to_string(),
+            Statement::Return(_) => "return".to_string(),
+        };
+        let size = info.end_offset - info.start_offset;
+        if size > 0 {
+            let frames = path_segments(function).join(";");
+            lines.push(format!("{frames};{libfunc} {size}"));
+        }
+    }
+    Ok(folded::merge([lines.join("\n").as_str()]))
+}
+
+/// Find a function by its full path or a path suffix, e.g. `crypto::verify`.
+/// If the function is an `#[executable]`, its wrapper is returned instead.
+pub fn find_function<'a>(program: &'a Program, name: &str) -> anyhow::Result<&'a Function> {
+    let wrapper = executable_wrapper_name(name);
+    let matches = |func: &Function, path: &str| {
+        func.id.debug_name.as_ref().is_some_and(|debug_name| {
+            debug_name.as_str() == path || debug_name.ends_with(&format!("::{path}"))
+        })
+    };
+    program
+        .funcs
+        .iter()
+        .find(|func| matches(func, &wrapper))
+        .or_else(|| program.funcs.iter().find(|func| matches(func, name)))
+        .with_context(|| format!("function `{name}` not found"))
+}
+
+/// Names of user functions which can be used as profiling entrypoints.
+pub fn entrypoint_names(program: &Program) -> Vec<String> {
+    user_functions(program)
+        .map(|func| func.id.to_string())
+        .collect()
+}
+
+/// List user functions which can be used as profiling entrypoints,
+/// along with their signatures without implicit arguments.
+pub fn list_functions(program: &Program) -> Vec<String> {
+    let user_types = |types: &[ConcreteTypeId]| {
+        types
+            .iter()
+            .map(|ty| ty.to_string())
+            .filter(|ty| !IMPLICIT_TYPES.contains(&ty.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut functions: Vec<String> = user_functions(program)
+        .map(|func| {
+            let name = func.id.to_string();
+            let wrapper = executable_wrapper_name(&name);
+            let executable = program
+                .funcs
+                .iter()
+                .any(|func| func.id.debug_name.as_deref() == Some(wrapper.as_str()));
+            format!(
+                "{}{name}({}) -> ({})",
+                if executable { "#[executable] " } else { "" },
+                user_types(&func.signature.param_types[..]),
+                user_types(&func.signature.ret_types[..]),
+            )
+        })
+        .collect();
+    functions.sort();
+    functions
+}
+
+/// Functions with debug names, except for corelib, executable wrappers and generated loops.
+fn user_functions(program: &Program) -> impl Iterator<Item = &Function> {
+    program.funcs.iter().filter(|func| {
+        func.id.debug_name.as_ref().is_some_and(|name| {
+            !name.starts_with("core::") && !name.contains(EXECUTABLE_PREFIX) && !name.ends_with(']')
+        })
+    })
+}
+
+fn executable_wrapper_name(name: &str) -> String {
+    match name.rsplit_once("::") {
+        Some((module, leaf)) => format!("{module}::{EXECUTABLE_PREFIX}{leaf}"),
+        None => format!("{EXECUTABLE_PREFIX}{name}"),
+    }
+}
+
+/// Find the user function wrapped by an executable wrapper, unless it has been inlined.
+pub fn executable_function<'a>(program: &'a Program, wrapper: &Function) -> Option<&'a Function> {
+    let name = wrapper
+        .id
+        .debug_name
+        .as_ref()?
+        .replacen(EXECUTABLE_PREFIX, "", 1);
+    program
+        .funcs
+        .iter()
+        .find(|func| func.id.debug_name.as_deref() == Some(name.as_str()))
+}
+
+pub fn is_executable_wrapper(func: &Function) -> bool {
+    func.id
+        .debug_name
+        .as_ref()
+        .is_some_and(|debug_name| debug_name.contains(EXECUTABLE_PREFIX))
+}
+
+/// Gas cost of a step, the same in the Sierra gas and the L2 gas models.
+pub const STEP_GAS: usize = 100;
+
+/// Trace cells of a step in the prover model, roughly the width of the opcode components.
+const STEP_CELLS: usize = 35;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Builtin {
+    RangeCheck,
+    Pedersen,
+    Poseidon,
+    Bitwise,
+    EcOp,
+}
+
+impl Builtin {
+    /// Builtin by the name reported by the VM, e.g. `range_check`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Builtin::RangeCheck,
+            Builtin::Pedersen,
+            Builtin::Poseidon,
+            Builtin::Bitwise,
+            Builtin::EcOp,
+        ]
+        .into_iter()
+        .find(|builtin| builtin.name() == name)
+    }
+
+    /// Name of the builtin as reported by the VM.
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::RangeCheck => "range_check",
+            Builtin::Pedersen => "pedersen",
+            Builtin::Poseidon => "poseidon",
+            Builtin::Bitwise => "bitwise",
+            Builtin::EcOp => "ec_op",
+        }
+    }
+
+    /// Gas cost of a single builtin usage. Sierra gas follows `token_gas_cost` of cairo-lang-sierra,
+    /// L2 gas follows the Starknet fee weights in L1 gas (e.g. 0.08 for pedersen)
+    /// at 40000 L2 gas per L1 gas. The prover model uses order-of-magnitude trace cell counts
+    /// of the builtin components, a cost table should be used for a specific prover.
+    pub fn gas(self, model: Weight) -> usize {
+        match (model, self) {
+            (Weight::Builtins, _) => 1,
+            (Weight::Memory, builtin) => builtin_cells(builtin.name()),
+            (Weight::Prover, Builtin::RangeCheck) => 12,
+            (Weight::Prover, Builtin::Bitwise) => 90,
+            (Weight::Prover, Builtin::Poseidon) => 420,
+            (Weight::Prover, Builtin::Pedersen) => 2300,
+            (Weight::Prover, Builtin::EcOp) => 12000,
+            (Weight::L2Gas, Builtin::RangeCheck) => 1600,
+            (Weight::L2Gas, Builtin::Pedersen | Builtin::Poseidon) => 3200,
+            (Weight::L2Gas, Builtin::Bitwise) => 6400,
+            (Weight::L2Gas, Builtin::EcOp) => 51200,
+            (_, Builtin::RangeCheck) => 70,
+            (_, Builtin::Pedersen) => 4050,
+            (_, Builtin::Poseidon) => 491,
+            (_, Builtin::Bitwise) => 583,
+            (_, Builtin::EcOp) => 4085,
+        }
+    }
+}
+
+/// Memory cells of a single builtin instance in its segment, by the VM builtin name.
+pub fn builtin_cells(name: &str) -> usize {
+    match name {
+        "pedersen" | "segment_arena" => 3,
+        "ecdsa" => 2,
+        "bitwise" => 5,
+        "ec_op" | "add_mod" | "mul_mod" => 7,
+        "poseidon" => 6,
+        "keccak" => 16,
+        _ => 1,
+    }
+}
+
+/// CASM steps of a single invocation of a builtin-using libfunc, along with the builtins it uses.
+/// The numbers follow the libfunc implementations in sierra-to-casm and are approximate
+/// for libfuncs with several branches.
+fn libfunc_builtins(libfunc: &str) -> Option<(usize, &'static [(Builtin, usize)])> {
+    // Integer libfuncs are named `<type>_<op>`, e.g. `u32_overflowing_add`
+    let generic = libfunc.split('<').next().unwrap_or(libfunc);
+    let int_op = generic
+        .strip_prefix(['u', 'i'])
+        .and_then(|rest| rest.split_once('_'))
+        .filter(|(bits, _)| bits.parse::<u32>().is_ok())
+        .map(|(_, op)| op);
+    let cost: (usize, &[(Builtin, usize)]) = match (generic, int_op) {
+        ("pedersen", _) => (2, &[(Builtin::Pedersen, 1)]),
+        ("hades_permutation", _) => (3, &[(Builtin::Poseidon, 1)]),
+        ("bitwise", _) => (2, &[(Builtin::Bitwise, 1)]),
+        ("ec_state_add_mul", _) => (5, &[(Builtin::EcOp, 1)]),
+        ("withdraw_gas" | "withdraw_gas_all", _) => (3, &[(Builtin::RangeCheck, 1)]),
+        (
+            _,
+            Some(
+                "overflowing_add"
+                | "overflowing_sub"
+                | "overflowing_add_impl"
+                | "overflowing_sub_impl",
+            ),
+        ) => (3, &[(Builtin::RangeCheck, 1)]),
+        (_, Some("try_from_felt252")) => (5, &[(Builtin::RangeCheck, 2)]),
+        (_, Some("safe_divmod")) => (6, &[(Builtin::RangeCheck, 3)]),
+        (_, Some("sqrt")) => (7, &[(Builtin::RangeCheck, 4)]),
+        _ => return None,
+    };
+    Some(cost)
+}
+
+/// Estimated builtin usages of a libfunc which executed the given number of steps.
+pub fn builtin_usages(libfunc: &str, steps: usize) -> Vec<(Builtin, usize)> {
+    let Some((casm_steps, builtins)) = libfunc_builtins(libfunc) else {
+        return Vec::new();
+    };
+    let invocations = steps.div_ceil(casm_steps);
+    builtins
+        .iter()
+        .map(|(builtin, usages)| (*builtin, invocations * usages))
+        .collect()
+}
+
+/// Estimated number of invocations of a libfunc which executed the given number of steps,
+/// known for builtin-using libfuncs and the ones compiled to a single instruction.
+pub fn invocations(libfunc: &str, steps: usize) -> Option<usize> {
+    let generic = libfunc.split('<').next().unwrap_or(libfunc);
+    let casm_steps = match generic {
+        "jump" | "function_call" | "return" => 1,
+        _ => libfunc_builtins(libfunc)?.0,
+    };
+    Some(steps.div_ceil(casm_steps))
 }
 
+/// The falcon program of the test data and the arguments of its `main`.
 #[cfg(test)]
-mod tests {
+pub(crate) fn falcon() -> (Program, Vec<Arg>) {
     use cairo_lang_utils::bigint::BigUintAsHex;
 
+    let source = include_str!("../tests/data/falcon.sierra.json");
+    let args_source = include_str!("../tests/data/falcon_args.json");
+    let program = serde_json::from_str::<VersionedProgram>(source)
+        .expect("failed to deserialize Sierra program")
+        .into_v1()
+        .expect("failed to convert to v1")
+        .program;
+    let args = serde_json::from_str::<Vec<BigUintAsHex>>(args_source)
+        .expect("failed to deserialize arguments")
+        .into_iter()
+        .map(|arg| Arg::Value(arg.value.into()))
+        .collect();
+    (program, args)
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
 
     #[test]
     fn test_adjust_weights() {
-        let source = include_str!("../tests/data/falcon.sierra.json");
-        let args_source = include_str!("../tests/data/falcon_args.json");
-        let program = serde_json::from_str::<VersionedProgram>(source)
-            .expect("failed to deserialize Sierra program");
-        let arguments = serde_json::from_str::<Vec<BigUintAsHex>>(args_source)
-            .expect("failed to deserialize arguments");
-        let args: Vec<Arg> = arguments
-            .into_iter()
-            .map(|arg| Arg::Value(arg.value.into()))
-            .collect();
-        let _ = profile(program, args).expect("failed to profile");
+        let (program, args) = falcon();
+        let _ = profile(program, "main", args, &Default::default()).expect("failed to profile");
+    }
+
+    #[test]
+    fn test_builtin_weights() {
+        // 2 invocations of 2 steps, 40.5 steps per pedersen
+        assert_eq!(Weight::Steps.weight(Some("pedersen"), 4, None), 85);
+        assert_eq!(
+            Weight::Steps.weight(Some("u32_overflowing_add"), 30, None),
+            37
+        );
+        assert_eq!(
+            Weight::Steps.weight(Some("i8_overflowing_sub_impl"), 3, None),
+            3
+        );
+        assert_eq!(
+            Weight::Steps.weight(Some("store_temp<felt252>"), 100, None),
+            100
+        );
+        assert_eq!(
+            Weight::SierraGas.weight(Some("pedersen"), 4, None),
+            8500
+        );
+        assert_eq!(Weight::L2Gas.weight(Some("pedersen"), 4, None), 6800);
+        assert_eq!(Weight::L2Gas.weight(None, 4, None), 400);
+        assert_eq!(Weight::Prover.weight(Some("pedersen"), 4, None), 4740);
+        assert_eq!(Weight::Builtins.weight(Some("pedersen"), 4, None), 2);
+        assert_eq!(Weight::Builtins.weight(None, 4, None), 0);
+    }
+
+    #[test]
+    fn test_cost_table() {
+        let table: CostTable = toml::from_str(
+            r#"
+            step = 10
+            [builtins]
+            pedersen = 1000
+            [libfuncs]
+            store_temp = 3
+            "#,
+        )
+        .unwrap();
+        let table = Some(&table);
+        assert_eq!(
+            Weight::SierraGas.weight(Some("pedersen"), 4, table),
+            2040
+        );
+        assert_eq!(Weight::Steps.weight(Some("pedersen"), 4, table), 204);
+        assert_eq!(
+            Weight::SierraGas.weight(Some("store_temp<felt252>"), 5, table),
+            15
+        );
+        assert_eq!(Weight::SierraGas.weight(None, 5, table), 50);
+    }
+
+    #[test]
+    fn test_weight_model() {
+        #[derive(Debug)]
+        struct Doubled;
+
+        impl WeightModel for Doubled {
+            fn name(&self) -> String {
+                "doubled".into()
+            }
+
+            fn weight(&self, _libfunc: Option<&str>, steps: usize) -> usize {
+                2 * steps
+            }
+        }
+
+        let options = ProfileOptions {
+            weight_model: Some(Arc::new(Doubled)),
+            ..Default::default()
+        };
+        assert_eq!(options.frame_weight(Some("pedersen"), 4), 8);
+        assert_eq!(options.syscall_weight("storage_read", 3), None);
+        assert_eq!(WeightModel::weight(&Weight::Steps, Some("pedersen"), 4), 85);
+        assert_eq!(Weight::SierraGas.name(), "sierra-gas");
+        assert_eq!(Weight::Prover.builtin(), Some(Weight::Prover));
+        assert_eq!(Doubled.builtin(), None);
+    }
+
+    #[test]
+    fn test_syscall_weights() {
+        assert_eq!(snake_case("StorageRead"), "storage_read");
+        assert_eq!(snake_case("SendMessageToL1"), "send_message_to_l1");
+        assert_eq!(
+            Weight::SierraGas.syscall_weight("call_contract", 2, None),
+            Some(320_000)
+        );
+        assert_eq!(
+            Weight::Steps.syscall_weight("storage_read", 3, None),
+            Some(300)
+        );
+        assert_eq!(
+            Weight::Prover.syscall_weight("storage_read", 3, None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_group_by() {
+        let function = "core::array::ArrayImpl::<core::felt252>::append";
+        assert_eq!(GroupBy::Crate.frame(function), "core");
+        assert_eq!(
+            GroupBy::Module.frame(function),
+            "core::array::ArrayImpl::<core::felt252>"
+        );
+        assert_eq!(GroupBy::Module.frame("main"), "main");
+        assert_eq!(GroupBy::Function.frame(function), function);
+    }
+
+    #[test]
+    fn test_strip_generics() {
+        assert_eq!(
+            strip_generics("core::array::ArrayImpl::<core::felt252>::append"),
+            "core::array::ArrayImpl::append"
+        );
+        assert_eq!(strip_generics("store_temp<Array<felt252>>"), "store_temp");
+        assert_eq!(strip_generics("main"), "main");
+    }
+
+    #[test]
+    fn test_logical_name() {
+        assert_eq!(logical_name("app::sum[expr12]::<core::integer::u32>"), "app::sum[expr]");
+        assert_eq!(logical_name("app::a[expr3]::b[expr45]"), "app::a[expr]::b[expr]");
+        assert_eq!(logical_name("function_call<user@app::main>"), "function_call");
+    }
+
+    #[test]
+    fn test_run_within_steps() {
+        // Runs taking a step per 130 gas, returning after `end` steps
+        let run = |gas: usize, end: usize| Ok((gas / 130).min(end));
+        let (steps, budget) =
+            run_within_steps(|gas| run(gas, 5000), |s| *s, usize::MAX, 1000).unwrap();
+        assert_eq!(steps, 1000);
+        assert!(budget > 1000 * STEP_GAS);
+        let (steps, _) = run_within_steps(|gas| run(gas, 500), |s| *s, usize::MAX, 1000).unwrap();
+        assert_eq!(steps, 500);
+        let (steps, budget) = run_within_steps(|gas| run(gas, 5000), |s| *s, 26_000, 1000).unwrap();
+        assert_eq!((steps, budget), (200, 26_000));
+
+        // Cheaper steps than the budget assumes are stopped by a smaller one
+        let run = |gas: usize| Ok(gas / 80);
+        let (steps, budget) = run_within_steps(run, |s| *s, usize::MAX, 1000).unwrap();
+        assert_eq!(steps, 1000);
+        assert!(budget < 1000 * STEP_GAS);
+    }
+
+    #[test]
+    fn test_emitted_events() {
+        let mut memory: Vec<Option<u128>> = vec![None; 20];
+        memory[1] = Some(EMIT_EVENT_SELECTOR);
+        memory[2] = Some(1000);
+        memory[3..7].copy_from_slice(&[Some(10), Some(12), Some(12), Some(15)]);
+        memory[10] = Some(0x99);
+        // Data holding the selector without spans following it
+        memory[13] = Some(EMIT_EVENT_SELECTOR);
+        assert_eq!(
+            emitted_events(&memory),
+            vec![EmittedEvent {
+                selector: Some(BigUint::from(0x99u8)),
+                keys: 2,
+                data: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_render_trace() {
+        let trace = ["app::main", "app::transfer", "core::panic_with_felt252"].map(String::from);
+        assert_eq!(
+            render_trace(&trace),
+            "   0: core::panic_with_felt252\n   1: app::transfer\n   2: app::main"
+        );
+    }
+
+    #[test]
+    fn test_folded_dimensions() {
+        let (program, args) = falcon();
+        let options = ProfileOptions {
+            dimensions: vec![Dimension::GenericLibfunc, Dimension::UserFunction],
+            ..Default::default()
+        };
+        let profile = profile(program, "main", args, &options).expect("failed to profile");
+        let folded = folded(&profile, &options);
+        assert!(folded
+            .lines()
+            .any(|line| line.starts_with("generic-libfunc;store_temp ")));
+        assert!(folded
+            .lines()
+            .any(|line| line.starts_with("user-function;falcon::")));
+        assert!(
+            folded
+                .lines()
+                .all(|line| line.starts_with("generic-libfunc;")
+                    || line.starts_with("user-function;"))
+        );
+    }
+
+    #[test]
+    fn test_code_size() {
+        let (program, _) = falcon();
+        let folded = code_size(&program).expect("failed to compute code size");
+        assert!(folded.lines().any(|line| line.starts_with("falcon;")));
+        assert!(folded::total_weight(&folded) > 0);
+    }
+
+    #[test]
+    fn test_list_functions() {
+        let (program, _) = falcon();
+        let functions = list_functions(&program);
+        assert!(functions
+            .iter()
+            .any(|f| f.starts_with("#[executable] falcon::main(")));
+        assert!(functions.iter().all(|f| !f.starts_with("core::")));
     }
 }
@@ -0,0 +1,1612 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::burnprofile::Node;
+use crate::folded;
+use crate::outln;
+use crate::output;
+#[cfg(feature = "runner")]
+use crate::profiler::{self, Profile};
+#[cfg(feature = "runner")]
+use cairo_lang_starknet_classes::contract_class::ContractClass;
+#[cfg(feature = "runner")]
+use cairo_lang_starknet_classes::keccak::starknet_keccak;
+
+/// Summary of the runs printed as a table or exported as JSON.
+pub trait Report: Serialize {
+    /// Name of the report used in messages.
+    const NAME: &'static str;
+
+    /// Print the report, tables are limited to the `top` entries.
+    fn print(&self, top: usize);
+
+    /// Export the report as JSON.
+    fn write(&self, path: &Utf8PathBuf) -> Result<()> {
+        output::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {} report: {path}", Self::NAME))
+    }
+}
+
+/// Steps of `felt252_dict_entry_get`, which writes a dict access of 3 cells.
+#[cfg(feature = "runner")]
+const DICT_ENTRY_GET_STEPS: usize = 2;
+#[cfg(feature = "runner")]
+const DICT_ACCESS_CELLS: usize = 3;
+
+/// Memory usage of the runs.
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct MemoryReport {
+    pub steps: usize,
+    /// Memory cells written.
+    pub memory_cells: usize,
+    /// Relocated memory size, including holes.
+    pub memory_size: usize,
+    pub memory_holes: usize,
+    /// Cells of the builtin segments per builtin.
+    pub builtin_segments: BTreeMap<String, usize>,
+    /// Estimated number of dict accesses.
+    pub dict_accesses: usize,
+}
+
+#[cfg(feature = "runner")]
+impl MemoryReport {
+    pub fn add(&mut self, profile: &Profile) {
+        let resources = &profile.resources.basic_resources;
+        self.steps += resources.n_steps;
+        self.memory_cells += profile.memory_cells;
+        self.memory_size += profile.memory_size;
+        self.memory_holes += resources.n_memory_holes;
+        for (builtin, count) in &resources.builtin_instance_counter {
+            let name = builtin.to_string();
+            *self.builtin_segments.entry(name.clone()).or_default() +=
+                count * profiler::builtin_cells(&name);
+        }
+        let weights = profile.info.scoped_sierra_statement_weights.iter();
+        for (stack, steps) in weights.flat_map(|weights| weights.iter()) {
+            if stack
+                .last()
+                .is_some_and(|libfunc| libfunc == "felt252_dict_entry_get")
+            {
+                self.dict_accesses += steps.div_ceil(DICT_ENTRY_GET_STEPS);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for MemoryReport {
+    const NAME: &'static str = "memory";
+
+    fn print(&self, _top: usize) {
+        outln!("Steps             {:>12}", self.steps);
+        outln!("Memory cells      {:>12}", self.memory_cells);
+        outln!("Memory size       {:>12}", self.memory_size);
+        outln!("Memory holes      {:>12}", self.memory_holes);
+        for (builtin, cells) in &self.builtin_segments {
+            outln!("{:<18}{cells:>12}", format!("{builtin} segment"));
+        }
+        outln!(
+            "Dict accesses     {:>12}  (estimated, {} cells each)",
+            self.dict_accesses, DICT_ACCESS_CELLS
+        );
+    }
+}
+
+/// Steps per concrete libfunc, independent of the call stacks.
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct LibfuncReport {
+    pub libfuncs: BTreeMap<String, LibfuncStats>,
+}
+
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct LibfuncStats {
+    pub steps: usize,
+    /// Estimated invocations, if the CASM of the libfunc is known.
+    pub invocations: Option<usize>,
+}
+
+#[cfg(feature = "runner")]
+impl LibfuncReport {
+    pub fn add(&mut self, profile: &Profile) {
+        let weights = profile.info.scoped_sierra_statement_weights.iter();
+        for (stack, steps) in weights.flat_map(|weights| weights.iter()) {
+            let Some(libfunc) = stack.last() else {
+                continue;
+            };
+            let stats = self.libfuncs.entry(libfunc.clone()).or_default();
+            stats.steps += steps;
+            stats.invocations = profiler::invocations(libfunc, stats.steps);
+        }
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for LibfuncReport {
+    const NAME: &'static str = "libfunc";
+
+    /// Print the top libfuncs by steps.
+    fn print(&self, top: usize) {
+        let mut libfuncs: Vec<_> = self.libfuncs.iter().collect();
+        libfuncs.sort_by(|a, b| b.1.steps.cmp(&a.1.steps).then(a.0.cmp(b.0)));
+        libfuncs.truncate(top);
+        let width = libfuncs
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["Libfunc".len()])
+            .max()
+            .unwrap_or_default();
+        outln!(
+            "{:<width$}  {:>12}  {:>14}",
+            "Libfunc", "Steps", "Calls (est.)"
+        );
+        for (name, stats) in libfuncs {
+            let invocations = stats
+                .invocations
+                .map_or("-".to_string(), |invocations| invocations.to_string());
+            outln!("{name:<width$}  {:>12}  {invocations:>14}", stats.steps);
+        }
+    }
+}
+
+/// Builtin usage of the runs, attributed to the functions calling builtin libfuncs.
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct BuiltinReport {
+    /// Usages per builtin as counted by the VM.
+    pub totals: BTreeMap<String, usize>,
+    /// Estimated usages per builtin and calling function.
+    pub functions: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+#[cfg(feature = "runner")]
+impl BuiltinReport {
+    pub fn add(&mut self, profile: &Profile) {
+        for (builtin, count) in &profile.resources.basic_resources.builtin_instance_counter {
+            *self.totals.entry(builtin.to_string()).or_default() += count;
+        }
+        let weights = profile.info.scoped_sierra_statement_weights.iter();
+        for (stack, steps) in weights.flat_map(|weights| weights.iter()) {
+            let [.., function, libfunc] = stack.as_slice() else {
+                continue;
+            };
+            for (builtin, usages) in profiler::builtin_usages(libfunc, *steps) {
+                *self
+                    .functions
+                    .entry(builtin.name().to_string())
+                    .or_default()
+                    .entry(function.clone())
+                    .or_default() += usages;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for BuiltinReport {
+    const NAME: &'static str = "builtin";
+
+    /// Print the totals along with the top functions using each builtin.
+    fn print(&self, top: usize) {
+        if self.totals.is_empty() {
+            outln!("No builtins used");
+            return;
+        }
+        let width = self
+            .totals
+            .keys()
+            .map(String::len)
+            .chain(["Builtin".len()])
+            .max()
+            .unwrap_or_default();
+        outln!(
+            "{:<width$}  {:>10}  Top functions (estimated)",
+            "Builtin", "Usages"
+        );
+        for (builtin, total) in &self.totals {
+            let mut functions: Vec<_> = self.functions.get(builtin).into_iter().flatten().collect();
+            functions.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            let functions = functions
+                .into_iter()
+                .take(top)
+                .map(|(function, usages)| format!("{function} ({usages})"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            outln!("{builtin:<width$}  {total:>10}  {functions}");
+        }
+    }
+}
+
+/// Syscalls of the runs along with their Sierra gas cost.
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct SyscallReport {
+    pub syscalls: BTreeMap<String, SyscallStats>,
+}
+
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct SyscallStats {
+    pub calls: usize,
+    /// Gas of the syscalls themselves, excluding the execution of called contracts.
+    pub gas: usize,
+}
+
+#[cfg(feature = "runner")]
+impl SyscallReport {
+    pub fn add(&mut self, profile: &Profile) {
+        for (name, calls) in profiler::syscall_counts(&profile.resources) {
+            let stats = self.syscalls.entry(name.clone()).or_default();
+            stats.calls += calls;
+            stats.gas += calls * profiler::syscall_gas(&name);
+        }
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for SyscallReport {
+    const NAME: &'static str = "syscall";
+
+    /// Print the top syscalls by gas.
+    fn print(&self, top: usize) {
+        if self.syscalls.is_empty() {
+            outln!("No syscalls used");
+            return;
+        }
+        let mut syscalls: Vec<_> = self.syscalls.iter().collect();
+        syscalls.sort_by(|a, b| b.1.gas.cmp(&a.1.gas).then(a.0.cmp(b.0)));
+        syscalls.truncate(top);
+        let width = syscalls
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["Syscall".len()])
+            .max()
+            .unwrap_or_default();
+        outln!("{:<width$}  {:>8}  {:>12}", "Syscall", "Calls", "Gas");
+        for (name, stats) in syscalls {
+            outln!("{name:<width$}  {:>8}  {:>12}", stats.calls, stats.gas);
+        }
+    }
+}
+
+/// Storage reads and writes per accessing site along with their Sierra gas cost. The accessed
+/// keys are not known, so a site is the storage variable if the stack goes through the member
+/// module generated for it, otherwise the closest function outside of corelib.
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct StorageReport {
+    pub sites: BTreeMap<String, StorageStats>,
+}
+
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct StorageStats {
+    pub reads: usize,
+    pub writes: usize,
+    pub gas: usize,
+}
+
+#[cfg(feature = "runner")]
+impl StorageReport {
+    pub fn add(&mut self, profile: &Profile) {
+        for (stack, name, calls) in profiler::syscall_calls(profile) {
+            if calls == 0 || !matches!(name.as_str(), "storage_read" | "storage_write") {
+                continue;
+            }
+            let stats = self.sites.entry(storage_site(stack)).or_default();
+            if name == "storage_read" {
+                stats.reads += calls;
+            } else {
+                stats.writes += calls;
+            }
+            stats.gas += calls * profiler::syscall_gas(&name);
+        }
+    }
+}
+
+/// Storage variable or function accessing the storage, e.g. `MyToken::balances`.
+#[cfg(feature = "runner")]
+fn storage_site(stack: &[String]) -> String {
+    let functions = &stack[..stack.len().saturating_sub(1)];
+    let variable = functions.iter().rev().find_map(|frame| {
+        let (contract, member) = frame.split_once("::__member_module_")?;
+        let name = member.split("::").next()?;
+        Some(format!("{}::{name}", folded::strip_generics(contract)))
+    });
+    variable.unwrap_or_else(|| calling_function(functions))
+}
+
+/// Closest function of the stack outside of corelib.
+#[cfg(feature = "runner")]
+fn calling_function(functions: &[String]) -> String {
+    functions
+        .iter()
+        .rev()
+        .find(|frame| !frame.starts_with("core::"))
+        .map_or_else(|| "unknown".into(), |frame| folded::strip_generics(frame))
+}
+
+/// Emitted events per event type along with the Sierra gas of the syscalls and the size of
+/// the keys and data. Events are matched to the types of the stacks by the selector of the
+/// type name, the others are listed by their selector.
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct EventReport {
+    pub events: BTreeMap<String, EventStats>,
+}
+
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct EventStats {
+    pub emits: usize,
+    pub gas: usize,
+    /// Felts of the keys and of the data of the events.
+    pub keys: usize,
+    pub data: usize,
+    /// L2 gas charged for the keys and the data, on top of the gas of the syscalls.
+    pub l2_gas: usize,
+}
+
+/// Factor of the L2 gas of an event key over a felt of event data.
+#[cfg(feature = "runner")]
+const EVENT_KEY_FACTOR: usize = 2;
+
+#[cfg(feature = "runner")]
+impl EventReport {
+    pub fn add(&mut self, profile: &Profile) {
+        let mut types = BTreeMap::new();
+        for (stack, name, calls) in profiler::syscall_calls(profile) {
+            if calls == 0 || name != "emit_event" {
+                continue;
+            }
+            let event = event_type(stack);
+            let type_name = event.rsplit("::").next().unwrap_or_default();
+            types.insert(starknet_keccak(type_name.as_bytes()), event.clone());
+            let stats = self.events.entry(event).or_default();
+            stats.emits += calls;
+            stats.gas += calls * profiler::syscall_gas(&name);
+        }
+        for event in &profile.events {
+            let name = match &event.selector {
+                Some(selector) => types
+                    .get(selector)
+                    .cloned()
+                    .unwrap_or_else(|| format!("selector {selector:#x}")),
+                None => "no keys".into(),
+            };
+            let stats = self.events.entry(name).or_default();
+            stats.keys += event.keys;
+            stats.data += event.data;
+            stats.l2_gas += (EVENT_KEY_FACTOR * event.keys + event.data) * CALLDATA_FELT_L2_GAS;
+        }
+    }
+}
+
+/// Event emitted by the stack, the type argument of `emit` if it has one, e.g.
+/// `token::MyToken::Transfer`, otherwise the function emitting it.
+#[cfg(feature = "runner")]
+fn event_type(stack: &[String]) -> String {
+    let functions = &stack[..stack.len().saturating_sub(1)];
+    let event = functions.iter().rev().find_map(|frame| {
+        let args = frame.split_once("::emit::<")?.1;
+        let event = args.split([',', '>']).next()?.trim();
+        (!event.is_empty()).then(|| event.to_string())
+    });
+    event.unwrap_or_else(|| calling_function(functions))
+}
+
+#[cfg(feature = "runner")]
+impl Report for EventReport {
+    const NAME: &'static str = "event";
+
+    /// Print the top events by the gas of the syscalls, then by the L2 gas of the data.
+    fn print(&self, top: usize) {
+        if self.events.is_empty() {
+            outln!("No events emitted");
+            return;
+        }
+        let mut events: Vec<_> = self.events.iter().collect();
+        let gas = |stats: &EventStats| (stats.gas, stats.l2_gas);
+        events.sort_by(|a, b| gas(b.1).cmp(&gas(a.1)).then(a.0.cmp(b.0)));
+        events.truncate(top);
+        let width = events
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["Event".len()])
+            .max()
+            .unwrap_or_default();
+        outln!(
+            "{:<width$}  {:>8}  {:>8}  {:>8}  {:>12}  {:>12}",
+            "Event", "Emits", "Keys", "Data", "Gas", "L2 gas"
+        );
+        for (name, stats) in events {
+            outln!(
+                "{name:<width$}  {:>8}  {:>8}  {:>8}  {:>12}  {:>12}",
+                stats.emits, stats.keys, stats.data, stats.gas, stats.l2_gas
+            );
+        }
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for StorageReport {
+    const NAME: &'static str = "storage";
+
+    /// Print the top storage access sites by gas.
+    fn print(&self, top: usize) {
+        if self.sites.is_empty() {
+            outln!("No storage accessed");
+            return;
+        }
+        let mut sites: Vec<_> = self.sites.iter().collect();
+        sites.sort_by(|a, b| b.1.gas.cmp(&a.1.gas).then(a.0.cmp(b.0)));
+        sites.truncate(top);
+        let width = sites
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["Storage".len()])
+            .max()
+            .unwrap_or_default();
+        outln!(
+            "{:<width$}  {:>8}  {:>8}  {:>12}",
+            "Storage", "Reads", "Writes", "Gas"
+        );
+        for (name, stats) in sites {
+            outln!(
+                "{name:<width$}  {:>8}  {:>8}  {:>12}",
+                stats.reads, stats.writes, stats.gas
+            );
+        }
+    }
+}
+
+/// L2 gas charged per felt of calldata.
+#[cfg(feature = "runner")]
+const CALLDATA_FELT_L2_GAS: usize = 5120;
+/// L1 data gas per felt of the state diff published in blobs, a byte costing a gas.
+#[cfg(feature = "runner")]
+const STATE_DIFF_FELT_DATA_GAS: usize = 32;
+
+/// Estimated fee of a transaction calling the profiled entrypoint, excluding
+/// the account validation and the fee transfer.
+#[cfg(feature = "runner")]
+#[derive(Serialize)]
+pub struct FeeReport {
+    /// L2 gas of the steps counted by the VM.
+    pub steps_l2_gas: usize,
+    /// L2 gas of the builtins counted by the VM.
+    pub builtins_l2_gas: usize,
+    pub syscalls_l2_gas: usize,
+    pub calldata_l2_gas: usize,
+    pub l2_gas: usize,
+    /// Storage writes, an upper bound of the storage diff as the written keys are not known.
+    pub storage_writes: usize,
+    /// Contracts deployed or replacing their class, each updating a class hash.
+    pub class_updates: usize,
+    /// Felts of the state diff published to L1: per updated contract its address and a
+    /// header, then its new class hash and its storage keys and values.
+    pub state_diff_felts: usize,
+    pub l1_data_gas: usize,
+    /// Gas prices and the fee in FRI.
+    pub l2_gas_price: u128,
+    pub l1_data_gas_price: u128,
+    pub fee: u128,
+}
+
+#[cfg(feature = "runner")]
+impl FeeReport {
+    pub fn new(
+        profile: &Profile,
+        calldata_len: usize,
+        l2_gas_price: u128,
+        l1_data_gas_price: u128,
+    ) -> Self {
+        let resources = &profile.resources.basic_resources;
+        let steps_l2_gas = resources.n_steps * profiler::STEP_GAS;
+        let builtins_l2_gas = resources
+            .builtin_instance_counter
+            .iter()
+            .filter_map(|(builtin, count)| {
+                let builtin = profiler::Builtin::from_name(&builtin.to_string())?;
+                Some(count * builtin.gas(profiler::Weight::L2Gas))
+            })
+            .sum();
+        let syscalls = profiler::syscall_counts(&profile.resources);
+        let syscalls_l2_gas = syscalls
+            .iter()
+            .map(|(name, calls)| calls * profiler::syscall_gas(name))
+            .sum();
+        let calldata_l2_gas = calldata_len * CALLDATA_FELT_L2_GAS;
+        let l2_gas = steps_l2_gas + builtins_l2_gas + syscalls_l2_gas + calldata_l2_gas;
+
+        let count = |syscall: &str| {
+            syscalls
+                .iter()
+                .find(|(name, _)| name == syscall)
+                .map_or(0, |(_, calls)| *calls)
+        };
+        let storage_writes = count("storage_write");
+        let deploys = count("deploy");
+        let class_updates = deploys + count("replace_class");
+        // The profiled contract is in the diff if it writes or replaces its class
+        let updates_itself = storage_writes > 0 || count("replace_class") > 0;
+        let contracts = usize::from(updates_itself) + deploys;
+        let state_diff_felts = 2 * contracts + class_updates + 2 * storage_writes;
+        let l1_data_gas = state_diff_felts * STATE_DIFF_FELT_DATA_GAS;
+
+        Self {
+            steps_l2_gas,
+            builtins_l2_gas,
+            syscalls_l2_gas,
+            calldata_l2_gas,
+            l2_gas,
+            storage_writes,
+            class_updates,
+            state_diff_felts,
+            l1_data_gas,
+            l2_gas_price,
+            l1_data_gas_price,
+            fee: l2_gas as u128 * l2_gas_price + l1_data_gas as u128 * l1_data_gas_price,
+        }
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for FeeReport {
+    const NAME: &'static str = "fee";
+
+    fn print(&self, _top: usize) {
+        outln!("Steps             {:>14} L2 gas", self.steps_l2_gas);
+        outln!("Builtins          {:>14} L2 gas", self.builtins_l2_gas);
+        outln!("Syscalls          {:>14} L2 gas", self.syscalls_l2_gas);
+        outln!("Calldata          {:>14} L2 gas", self.calldata_l2_gas);
+        outln!(
+            "State diff        {:>14} L1 data gas  ({} felts: at most {} storage writes, \
+            {} class updates)",
+            self.l1_data_gas, self.state_diff_felts, self.storage_writes, self.class_updates
+        );
+        outln!(
+            "L2 gas            {:>14} x {} FRI",
+            self.l2_gas, self.l2_gas_price
+        );
+        outln!(
+            "L1 data gas       {:>14} x {} FRI",
+            self.l1_data_gas, self.l1_data_gas_price
+        );
+        outln!(
+            "Estimated fee     {:>14} FRI  ({:.6} STRK)",
+            self.fee,
+            self.fee as f64 / 1e18
+        );
+    }
+}
+
+/// L2 gas per byte of the code of a declared class, 0.875 L1 gas after the Starknet versioned
+/// constants at 40000 L2 gas per L1 gas.
+#[cfg(feature = "runner")]
+const CODE_BYTE_L2_GAS: usize = 35_000;
+
+/// Size of a contract class and the estimated cost of declaring it. The code of a class is its
+/// Sierra program and CASM bytecode at 32 bytes per felt and its ABI.
+#[cfg(feature = "runner")]
+#[derive(Serialize)]
+pub struct ClassReport {
+    pub sierra_felts: usize,
+    pub casm_felts: usize,
+    pub abi_bytes: usize,
+    pub code_bytes: usize,
+    /// L2 gas of the code, excluding the account validation and the declare transaction.
+    pub l2_gas: usize,
+    pub l2_gas_price: u128,
+    pub fee: u128,
+    /// CASM felts per module, or impl, of the functions.
+    pub modules: BTreeMap<String, usize>,
+}
+
+#[cfg(feature = "runner")]
+impl ClassReport {
+    pub fn new(contract_class: &ContractClass, l2_gas_price: u128) -> Result<Self> {
+        let program = contract_class
+            .extract_sierra_program()
+            .with_context(|| "failed to extract Sierra program from contract class")?;
+        let code_size = profiler::code_size(&program)?;
+        let mut modules = BTreeMap::new();
+        for (stack, size) in folded::lines(&code_size) {
+            // Stacks are the path segments of the function followed by the libfunc
+            let frames: Vec<&str> = stack.split(';').collect();
+            let module = frames[..frames.len().saturating_sub(2)].join("::");
+            *modules.entry(module).or_default() += size;
+        }
+        let sierra_felts = contract_class.sierra_program.len();
+        let casm_felts = folded::total_weight(&code_size);
+        let abi_bytes = match &contract_class.abi {
+            Some(abi) => serde_json::to_string(abi)?.len(),
+            None => 0,
+        };
+        let code_bytes = 32 * (sierra_felts + casm_felts) + abi_bytes;
+        let l2_gas = code_bytes * CODE_BYTE_L2_GAS;
+        Ok(Self {
+            sierra_felts,
+            casm_felts,
+            abi_bytes,
+            code_bytes,
+            l2_gas,
+            l2_gas_price,
+            fee: l2_gas as u128 * l2_gas_price,
+            modules,
+        })
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for ClassReport {
+    const NAME: &'static str = "class size";
+
+    /// Print the sizes and the top modules by CASM size.
+    fn print(&self, top: usize) {
+        outln!("Sierra program    {:>14} felts", self.sierra_felts);
+        outln!("CASM bytecode     {:>14} felts", self.casm_felts);
+        outln!("ABI               {:>14} bytes", self.abi_bytes);
+        outln!(
+            "Declare           {:>14} L2 gas  ({} code bytes)",
+            self.l2_gas, self.code_bytes
+        );
+        outln!(
+            "Estimated fee     {:>14} FRI  ({:.6} STRK)",
+            self.fee,
+            self.fee as f64 / 1e18
+        );
+        let mut modules: Vec<_> = self.modules.iter().collect();
+        modules.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        modules.truncate(top);
+        let width = modules
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["Module".len()])
+            .max()
+            .unwrap_or_default();
+        outln!();
+        outln!("{:<width$}  {:>10}", "Module", "CASM felts");
+        for (module, size) in modules {
+            outln!("{module:<width$}  {size:>10}");
+        }
+    }
+}
+
+/// Relative difference above which a local resource is flagged as diverging from the receipt.
+#[cfg(feature = "runner")]
+const DIVERGENCE: f64 = 0.1;
+
+/// Resources of the run next to the ones of an on-chain receipt, by the names of the receipt.
+/// The receipt includes the account validation and the fee transfer, so it is usually higher.
+#[cfg(feature = "runner")]
+#[derive(Serialize)]
+pub struct VerifyReport {
+    pub resources: BTreeMap<String, ResourceDelta>,
+}
+
+#[cfg(feature = "runner")]
+#[derive(Serialize)]
+pub struct ResourceDelta {
+    pub local: u64,
+    pub onchain: u64,
+    pub delta: i128,
+    /// Whether the difference is over 10% of the on-chain value.
+    pub diverges: bool,
+}
+
+#[cfg(feature = "runner")]
+impl VerifyReport {
+    /// Resources of the receipt the run has a counterpart of, builtins not used by the run
+    /// count as zero.
+    pub fn new(profile: &Profile, calldata_len: usize, onchain: &BTreeMap<String, u64>) -> Self {
+        let resources = &profile.resources.basic_resources;
+        let fee = FeeReport::new(profile, calldata_len, 0, 0);
+        let mut local = BTreeMap::from([
+            ("steps".to_string(), resources.n_steps),
+            ("memory_holes".to_string(), resources.n_memory_holes),
+            ("l2_gas".to_string(), fee.l2_gas),
+            ("l1_data_gas".to_string(), fee.l1_data_gas),
+            ("data_availability.l1_data_gas".to_string(), fee.l1_data_gas),
+        ]);
+        for (builtin, count) in &resources.builtin_instance_counter {
+            let builtin = builtin.to_string();
+            let name = builtin.strip_suffix("_builtin").unwrap_or(&builtin);
+            local.insert(format!("{name}_builtin_applications"), *count);
+        }
+        let resources = onchain
+            .iter()
+            .filter_map(|(name, &onchain)| {
+                let local = match local.get(name) {
+                    Some(&local) => local as u64,
+                    None if name.ends_with("_applications") => 0,
+                    None => return None,
+                };
+                let delta = i128::from(local) - i128::from(onchain);
+                let diverges = delta.unsigned_abs() as f64 > onchain as f64 * DIVERGENCE;
+                Some((
+                    name.clone(),
+                    ResourceDelta {
+                        local,
+                        onchain,
+                        delta,
+                        diverges,
+                    },
+                ))
+            })
+            .collect();
+        Self { resources }
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for VerifyReport {
+    const NAME: &'static str = "verification";
+
+    fn print(&self, _top: usize) {
+        if self.resources.is_empty() {
+            outln!("The receipt has no resources to compare with");
+            return;
+        }
+        let width = self
+            .resources
+            .keys()
+            .map(String::len)
+            .chain(["Resource".len()])
+            .max()
+            .unwrap_or_default();
+        outln!(
+            "{:<width$}  {:>12}  {:>12}  {:>12}",
+            "Resource", "Local", "On-chain", "Delta"
+        );
+        for (name, resource) in &self.resources {
+            outln!(
+                "{name:<width$}  {:>12}  {:>12}  {:>+12}{}",
+                resource.local,
+                resource.onchain,
+                resource.delta,
+                if resource.diverges { "  diverges" } else { "" }
+            );
+        }
+    }
+}
+
+/// Inclusive (subtree) and exclusive (self) weights per function of a folded profile.
+#[derive(Default, Serialize)]
+pub struct FunctionReport {
+    pub total: usize,
+    pub functions: BTreeMap<String, FunctionWeights>,
+}
+
+#[derive(Default, Serialize)]
+pub struct FunctionWeights {
+    pub inclusive: usize,
+    pub exclusive: usize,
+}
+
+impl FunctionReport {
+    /// The leaf of each stack is a libfunc, its weight is the exclusive weight of the caller.
+    pub fn new(folded: &str) -> Self {
+        let mut report = Self::default();
+        for (stack, weight) in folded::lines(folded) {
+            report.total += weight;
+            let frames: Vec<&str> = stack.split(';').collect();
+            let Some((_, functions)) = frames.split_last() else {
+                continue;
+            };
+            // Recursive functions are counted once per stack
+            let mut seen = HashSet::new();
+            for function in functions.iter().filter(|function| seen.insert(**function)) {
+                report
+                    .functions
+                    .entry(function.to_string())
+                    .or_default()
+                    .inclusive += weight;
+            }
+            if let Some(function) = functions.last() {
+                report
+                    .functions
+                    .entry(function.to_string())
+                    .or_default()
+                    .exclusive += weight;
+            }
+        }
+        report
+    }
+}
+
+impl Report for FunctionReport {
+    const NAME: &'static str = "function";
+
+    /// Print the top functions by inclusive weight.
+    fn print(&self, top: usize) {
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.1.inclusive.cmp(&a.1.inclusive).then(a.0.cmp(b.0)));
+        functions.truncate(top);
+        let width = functions
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["Function".len()])
+            .max()
+            .unwrap_or_default();
+        let percent = |weight: usize| 100.0 * weight as f64 / self.total.max(1) as f64;
+        outln!(
+            "{:<width$}  {:>12}  {:>7}  {:>12}  {:>7}",
+            "Function", "Inclusive", "%", "Exclusive", "%"
+        );
+        for (name, weights) in functions {
+            outln!(
+                "{name:<width$}  {:>12}  {:>6.2}%  {:>12}  {:>6.2}%",
+                weights.inclusive,
+                percent(weights.inclusive),
+                weights.exclusive,
+                percent(weights.exclusive)
+            );
+        }
+    }
+}
+
+/// Calls per function and call stack, counted from the `function_call` libfuncs
+/// which execute a single step per call, the entrypoint is called once per run.
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct CallReport {
+    /// Calls per call stack ending with the called function, frames separated by `;`.
+    pub stacks: BTreeMap<String, usize>,
+    pub functions: BTreeMap<String, CallStats>,
+}
+
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct CallStats {
+    pub calls: usize,
+    /// Inclusive weight of the function in the rendered profile.
+    pub weight: usize,
+}
+
+#[cfg(feature = "runner")]
+impl CallReport {
+    pub fn new(profiles: &[Profile], folded: &str) -> Self {
+        let mut report = Self::default();
+        for profile in profiles {
+            let weights = profile.info.scoped_sierra_statement_weights.iter();
+            let mut entrypoint = None;
+            for (stack, steps) in weights.flat_map(|weights| weights.iter()) {
+                entrypoint = entrypoint.or(stack.first());
+                let Some((libfunc, callers)) = stack.split_last() else {
+                    continue;
+                };
+                let Some(function) = libfunc
+                    .strip_prefix("function_call<user@")
+                    .and_then(|name| name.strip_suffix('>'))
+                else {
+                    continue;
+                };
+                report.add_calls(callers, function, *steps);
+            }
+            if let Some(entrypoint) = entrypoint {
+                report.add_calls(&[], entrypoint, 1);
+            }
+        }
+        for (function, weights) in FunctionReport::new(folded).functions {
+            if let Some(stats) = report.functions.get_mut(&function) {
+                stats.weight = weights.inclusive;
+            }
+        }
+        report
+    }
+
+    fn add_calls(&mut self, callers: &[String], function: &str, calls: usize) {
+        let stack = callers
+            .iter()
+            .map(String::as_str)
+            .chain([function])
+            .collect::<Vec<_>>()
+            .join(";");
+        *self.stacks.entry(stack).or_default() += calls;
+        self.functions
+            .entry(function.to_string())
+            .or_default()
+            .calls += calls;
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for CallReport {
+    const NAME: &'static str = "call";
+
+    /// Print the top functions by calls along with their weight per call.
+    fn print(&self, top: usize) {
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.1.calls.cmp(&a.1.calls).then(a.0.cmp(b.0)));
+        functions.truncate(top);
+        let width = functions
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["Function".len()])
+            .max()
+            .unwrap_or_default();
+        outln!(
+            "{:<width$}  {:>10}  {:>12}  {:>10}",
+            "Function", "Calls", "Weight", "Per call"
+        );
+        for (name, stats) in functions {
+            outln!(
+                "{name:<width$}  {:>10}  {:>12}  {:>10}",
+                stats.calls,
+                stats.weight,
+                stats.weight / stats.calls
+            );
+        }
+    }
+}
+
+/// Loops, i.e. directly recursive functions, of the scoped stacks along with their iterations.
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct LoopReport {
+    /// Loops by the call stack ending with the recursive function, frames separated by `;`.
+    pub loops: BTreeMap<String, LoopStats>,
+}
+
+#[cfg(feature = "runner")]
+#[derive(Default, Serialize)]
+pub struct LoopStats {
+    /// Times the loop was entered from its caller.
+    pub entries: usize,
+    /// Entries and recursive calls.
+    pub iterations: usize,
+    /// Inclusive weight of the loop in the rendered profile.
+    pub weight: usize,
+}
+
+#[cfg(feature = "runner")]
+impl LoopReport {
+    pub fn new(profiles: &[Profile], folded: &str) -> Self {
+        let calls = CallReport::new(profiles, folded);
+        let mut report = Self::default();
+        for (stack, recursive_calls) in &calls.stacks {
+            let Some((loop_stack, function)) = stack.rsplit_once(';') else {
+                continue;
+            };
+            if loop_stack.rsplit(';').next() != Some(function) {
+                continue;
+            }
+            let entries = calls.stacks.get(loop_stack).copied().unwrap_or(1);
+            let weight = folded::lines(folded)
+                .filter(|(frames, _)| {
+                    frames
+                        .strip_prefix(loop_stack)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with(';'))
+                })
+                .map(|(_, weight)| weight)
+                .sum();
+            report.loops.insert(
+                loop_stack.to_string(),
+                LoopStats {
+                    entries,
+                    iterations: entries + recursive_calls,
+                    weight,
+                },
+            );
+        }
+        report
+    }
+}
+
+#[cfg(feature = "runner")]
+impl Report for LoopReport {
+    const NAME: &'static str = "loop";
+
+    /// Print the hottest loops along with their cost per iteration.
+    fn print(&self, top: usize) {
+        if self.loops.is_empty() {
+            outln!("No loops found");
+            return;
+        }
+        let mut loops: Vec<_> = self.loops.iter().collect();
+        loops.sort_by(|a, b| b.1.weight.cmp(&a.1.weight).then(a.0.cmp(b.0)));
+        loops.truncate(top);
+        // Loops are named by their function, the full stack is in the JSON export
+        let name = |stack: &str| stack.rsplit(';').next().unwrap_or(stack).to_string();
+        let width = loops
+            .iter()
+            .map(|(stack, _)| name(stack).len())
+            .chain(["Loop".len()])
+            .max()
+            .unwrap_or_default();
+        outln!(
+            "{:<width$}  {:>8}  {:>12}  {:>12}  {:>14}",
+            "Loop", "Entries", "Iterations", "Weight", "Per iteration"
+        );
+        for (stack, stats) in loops {
+            outln!(
+                "{:<width$}  {:>8}  {:>12}  {:>12}  {:>14}",
+                name(stack),
+                stats.entries,
+                stats.iterations,
+                stats.weight,
+                stats.weight / stats.iterations.max(1)
+            );
+        }
+    }
+}
+
+/// Hottest functions by exclusive weight, a shorter view of the function report.
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct HotspotReport(pub FunctionReport);
+
+impl Report for HotspotReport {
+    const NAME: &'static str = "hotspot";
+
+    /// Print the top functions by exclusive weight with their share of the total.
+    fn print(&self, top: usize) {
+        let FunctionReport { total, functions } = &self.0;
+        let mut functions: Vec<_> = functions
+            .iter()
+            .filter(|(_, weights)| weights.exclusive > 0)
+            .collect();
+        functions.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(b.0)));
+        functions.truncate(top);
+        outln!("Top {} of total weight {total}:", functions.len());
+        for (name, weights) in functions {
+            let percent = 100.0 * weights.exclusive as f64 / (*total).max(1) as f64;
+            outln!("{percent:>6.2}%  {:>12}  {name}", weights.exclusive);
+        }
+    }
+}
+
+/// Call chains of a folded profile by their exclusive weight, libfunc leaves merged into them.
+#[derive(Default, Serialize)]
+pub struct PathReport {
+    pub total: usize,
+    /// Paths sorted by descending weight, frames separated by `;`.
+    pub paths: Vec<(String, usize)>,
+}
+
+impl PathReport {
+    pub fn new(folded: &str) -> Self {
+        let total = folded::total_weight(folded);
+        let mut weights: BTreeMap<&str, usize> = BTreeMap::new();
+        for (stack, weight) in folded::lines(folded) {
+            let path = stack.rsplit_once(';').map_or(stack, |(path, _)| path);
+            *weights.entry(path).or_default() += weight;
+        }
+        let mut paths: Vec<_> = weights
+            .into_iter()
+            .map(|(path, weight)| (path.to_string(), weight))
+            .collect();
+        paths.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        Self { total, paths }
+    }
+}
+
+impl Report for PathReport {
+    const NAME: &'static str = "path";
+
+    /// Print the top paths as call chains.
+    fn print(&self, top: usize) {
+        for (idx, (path, weight)) in self.paths.iter().take(top).enumerate() {
+            let percent = 100.0 * *weight as f64 / self.total.max(1) as f64;
+            outln!(
+                "{:>3}. {weight:>12} {percent:>6.2}%  {}",
+                idx + 1,
+                path.replace(';', " -> ")
+            );
+        }
+    }
+}
+
+/// Weight of a profile and a baseline profile it is compared with.
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct Delta {
+    pub base: usize,
+    pub current: usize,
+}
+
+impl Delta {
+    /// Relative change in percent, infinite if the base weight is zero.
+    pub fn percent(&self) -> f64 {
+        if self.base == self.current {
+            return 0.0;
+        }
+        100.0 * (self.current as f64 - self.base as f64) / self.base as f64
+    }
+}
+
+/// Changes of the total and the inclusive function weights of a folded profile relative
+/// to a baseline profile, functions missing from either of them weigh zero in it.
+#[derive(Default, Serialize)]
+pub struct DeltaReport {
+    pub total: Delta,
+    /// Functions sorted by the descending absolute change of their weight.
+    pub functions: Vec<(String, Delta)>,
+}
+
+impl DeltaReport {
+    pub fn new(base: &str, current: &str) -> Self {
+        let (base, current) = (FunctionReport::new(base), FunctionReport::new(current));
+        let mut functions: BTreeMap<String, Delta> = BTreeMap::new();
+        for (name, weights) in base.functions {
+            functions.entry(name).or_default().base = weights.inclusive;
+        }
+        for (name, weights) in current.functions {
+            functions.entry(name).or_default().current = weights.inclusive;
+        }
+        let mut functions: Vec<_> = functions
+            .into_iter()
+            .filter(|(_, delta)| delta.base != delta.current)
+            .collect();
+        functions.sort_by_key(|(_, delta)| std::cmp::Reverse(delta.base.abs_diff(delta.current)));
+        Self {
+            total: Delta {
+                base: base.total,
+                current: current.total,
+            },
+            functions,
+        }
+    }
+}
+
+impl Report for DeltaReport {
+    const NAME: &'static str = "delta";
+
+    /// Print the total change and the top changed functions.
+    fn print(&self, top: usize) {
+        let Delta { base, current } = self.total;
+        outln!(
+            "Total weight {base} -> {current} ({:+} {:+.2}%)",
+            current as i64 - base as i64,
+            self.total.percent()
+        );
+        if self.functions.is_empty() {
+            return;
+        }
+        let width = self
+            .functions
+            .iter()
+            .take(top)
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or_default()
+            .max(8);
+        outln!(
+            "{:<width$}  {:>12}  {:>12}  {:>12}  {:>8}",
+            "function", "base", "current", "delta", "change"
+        );
+        for (name, delta) in self.functions.iter().take(top) {
+            outln!(
+                "{name:<width$}  {:>12}  {:>12}  {:>+12}  {:>+7.2}%",
+                delta.base,
+                delta.current,
+                delta.current as i64 - delta.base as i64,
+                delta.percent()
+            );
+        }
+    }
+}
+
+/// Kind of work a libfunc does, checked in order.
+fn libfunc_category(libfunc: &str) -> &'static str {
+    let generic = libfunc.split('<').next().unwrap_or(libfunc);
+    let has = |prefixes: &[&str]| prefixes.iter().any(|prefix| generic.starts_with(prefix));
+    if generic.starts_with("syscall::") || generic.ends_with("_syscall") {
+        "syscalls"
+    } else if has(&["pedersen", "hades_permutation", "poseidon", "keccak", "sha256", "blake"]) {
+        "hashing"
+    } else if generic.contains("dict") || generic.contains("squash") {
+        "dict"
+    } else if has(&["withdraw_gas", "redeposit_gas", "get_builtin_costs", "get_available_gas"]) {
+        "gas"
+    } else if has(&[
+        "jump",
+        "function_call",
+        "return",
+        "branch_align",
+        "enum_match",
+        "enable_ap_tracking",
+        "disable_ap_tracking",
+    ]) {
+        "control flow"
+    } else if has(&[
+        "store_", "alloc_local", "finalize_locals", "rename", "dup", "drop", "array_", "span_",
+        "struct_", "enum_", "snapshot_take", "box", "into_box", "unbox", "local_into_box",
+        "const_as_",
+    ]) {
+        "memory"
+    } else if has(&[
+        "u8", "u16", "u32", "u64", "u128", "u256", "u512", "i8", "i16", "i32", "i64", "i128",
+        "felt252", "bytes31", "bounded_int", "downcast", "upcast", "ec_", "unwrap_non_zero",
+    ]) {
+        "arithmetic"
+    } else {
+        "other"
+    }
+}
+
+/// Weights per libfunc category of every top-level function, i.e. called by the entrypoint.
+#[derive(Default, Serialize)]
+pub struct CategoryReport {
+    pub functions: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl CategoryReport {
+    pub fn new(folded: &str) -> Self {
+        let mut report = Self::default();
+        for (stack, weight) in folded::lines(folded) {
+            let frames: Vec<&str> = stack.split(';').collect();
+            let Some((libfunc, functions)) = frames.split_last() else {
+                continue;
+            };
+            // Weight of the entrypoint itself is kept under its own name
+            let Some(function) = functions.get(1).or(functions.first()) else {
+                continue;
+            };
+            *report
+                .functions
+                .entry(function.to_string())
+                .or_default()
+                .entry(libfunc_category(libfunc).to_string())
+                .or_default() += weight;
+        }
+        report
+    }
+}
+
+impl Report for CategoryReport {
+    const NAME: &'static str = "category";
+
+    /// Print the category shares of the top functions by weight.
+    fn print(&self, top: usize) {
+        const BAR_WIDTH: usize = 40;
+        let mut functions: Vec<_> = self
+            .functions
+            .iter()
+            .map(|(name, categories)| (name, categories, categories.values().sum::<usize>()))
+            .collect();
+        functions.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+        for (name, categories, total) in functions.into_iter().take(top) {
+            outln!("{name} ({total})");
+            let mut categories: Vec<_> = categories.iter().collect();
+            categories.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            for (category, weight) in categories {
+                let share = *weight as f64 / total.max(1) as f64;
+                let bar = "#".repeat((share * BAR_WIDTH as f64).round() as usize);
+                outln!("  {category:<14} {:>6.2}%  {bar}", 100.0 * share);
+            }
+        }
+    }
+}
+
+/// Result and resources of a run.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RunSummary {
+    /// Felts returned by the function, the panic data if it ran out of gas.
+    pub return_values: Vec<String>,
+    pub panicked: bool,
+    /// Panic data decoded as short strings, `None` unless the run panicked unexpectedly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub panic_message: Option<String>,
+    /// Gas left by gas-enabled programs.
+    pub remaining_gas: Option<usize>,
+    pub steps: usize,
+    pub memory_holes: usize,
+    /// Builtin usages counted by the VM.
+    pub builtins: BTreeMap<String, usize>,
+    /// Calls per syscall, e.g. `storage_read`.
+    pub syscalls: BTreeMap<String, usize>,
+}
+
+#[cfg(feature = "runner")]
+impl RunSummary {
+    pub fn new(profile: &Profile) -> Self {
+        let resources = &profile.resources.basic_resources;
+        Self {
+            return_values: profile.return_values.clone(),
+            panicked: profile.panicked,
+            panic_message: profile.panic_message.clone(),
+            remaining_gas: profile.remaining_gas,
+            steps: resources.n_steps,
+            memory_holes: resources.n_memory_holes,
+            builtins: resources
+                .builtin_instance_counter
+                .iter()
+                .map(|(builtin, count)| (builtin.to_string(), *count))
+                .collect(),
+            syscalls: profiler::syscall_counts(&profile.resources)
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.panic_message {
+            Some(msg) => write!(f, "Panicked with [{msg}], {} steps", self.steps)?,
+            None => {
+                let verb = if self.panicked { "Panicked with" } else { "Returned" };
+                write!(f, "{verb} [{}], {} steps", self.return_values.join(", "), self.steps)?;
+            }
+        }
+        if let Some(gas) = self.remaining_gas {
+            write!(f, ", {gas} gas remaining")?;
+        }
+        for (builtin, count) in &self.builtins {
+            write!(f, ", {count} {builtin}")?;
+        }
+        for (syscall, calls) in &self.syscalls {
+            write!(f, ", {calls} {syscall}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a profile comes from, to attribute it and reproduce the run.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RunMetadata {
+    /// Name and version of the profiled package, `None` for programs given by path.
+    pub package: Option<String>,
+    pub version: Option<String>,
+    /// Commit checked out during the run, `None` outside of a git repository.
+    pub commit: Option<String>,
+    /// Hashes of the profiled program and its arguments, see `burnprofile::hash`.
+    pub program_hash: Option<String>,
+    pub arguments_hash: Option<String>,
+    /// Weight model of the profile, e.g. `steps`.
+    pub weight: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+impl RunMetadata {
+    /// `key: value` lines of the known fields, for the comments of the output formats.
+    pub fn comments(&self) -> Vec<String> {
+        let package = self.package.as_ref().map(|package| match &self.version {
+            Some(version) => format!("{package} {version}"),
+            None => package.clone(),
+        });
+        [
+            ("package", package),
+            ("commit", self.commit.clone()),
+            ("program hash", self.program_hash.clone()),
+            ("arguments hash", self.arguments_hash.clone()),
+            ("weight", self.weight.clone()),
+            ("timestamp", Some(self.timestamp.to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some(format!("{key}: {}", value?)))
+        .collect()
+    }
+}
+
+/// Version of the profile report, bumped on incompatible changes of its layout.
+const REPORT_VERSION: u32 = 2;
+
+/// Structured profile returned by the library and written by the `json` output type.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProfileReport {
+    pub version: u32,
+    /// Weight model of the values, e.g. `steps`, `None` if unknown.
+    pub weight: Option<String>,
+    pub total: usize,
+    pub functions: BTreeMap<String, FunctionSummary>,
+    /// Builtin usages counted by the VM, empty for profiles not produced by running.
+    pub builtins: BTreeMap<String, usize>,
+    /// Tree of the stacks with inclusive weights under an `all` root.
+    pub tree: Node,
+    /// Results of the profiled runs, empty for profiles not produced by running.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runs: Vec<RunSummary>,
+    /// Origin of the profile, `None` for profiles not produced by running.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RunMetadata>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct FunctionSummary {
+    pub inclusive: usize,
+    pub exclusive: usize,
+    /// Calls counted from the `function_call` libfuncs, if any.
+    pub calls: Option<usize>,
+}
+
+impl ProfileReport {
+    pub fn new(folded: &str, weight: Option<String>, builtins: BTreeMap<String, usize>) -> Self {
+        let calls = folded::calls_per_function(folded);
+        let report = FunctionReport::new(folded);
+        let functions = report
+            .functions
+            .into_iter()
+            .map(|(name, weights)| {
+                let function = FunctionSummary {
+                    inclusive: weights.inclusive,
+                    exclusive: weights.exclusive,
+                    calls: calls.get(name.as_str()).copied(),
+                };
+                (name, function)
+            })
+            .collect();
+        Self {
+            version: REPORT_VERSION,
+            weight,
+            total: report.total,
+            functions,
+            builtins,
+            tree: Node::from_folded(folded),
+            runs: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    pub fn with_runs(self, runs: Vec<RunSummary>) -> Self {
+        Self { runs, ..self }
+    }
+
+    pub fn with_metadata(self, metadata: Option<RunMetadata>) -> Self {
+        Self { metadata, ..self }
+    }
+
+    /// Folded stacks of the tree, for the writers taking folded profiles.
+    pub fn folded(&self) -> String {
+        self.tree.folded()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_function_report() {
+        let report =
+            FunctionReport::new("main;foo;store_temp 10\nmain;foo;foo;jump 5\nmain;return 1");
+        assert_eq!(report.total, 16);
+        assert_eq!(report.functions["main"].inclusive, 16);
+        assert_eq!(report.functions["main"].exclusive, 1);
+        assert_eq!(report.functions["foo"].inclusive, 15);
+        assert_eq!(report.functions["foo"].exclusive, 15);
+    }
+
+    #[test]
+    fn test_profile_report() {
+        let folded = "main;foo;store_temp 4\nmain;foo;jump 6\nmain;jump 1\n";
+        let report = ProfileReport::new(folded, Some("steps".into()), BTreeMap::new());
+        assert_eq!(report.total, 11);
+        assert_eq!(report.tree.value, 11);
+        assert_eq!(report.functions["foo"].exclusive, 10);
+        assert_eq!(report.folded(), folded);
+
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: ProfileReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.functions, report.functions);
+        assert_eq!(parsed.tree, report.tree);
+
+        let run = RunSummary {
+            return_values: vec!["1".into(), "2".into()],
+            remaining_gas: Some(100),
+            steps: 12,
+            builtins: BTreeMap::from([("range_check_builtin".to_string(), 3)]),
+            ..Default::default()
+        };
+        assert_eq!(
+            run.to_string(),
+            "Returned [1, 2], 12 steps, 100 gas remaining, 3 range_check_builtin"
+        );
+        let report = report.with_runs(vec![run.clone()]);
+        let parsed: ProfileReport =
+            serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        assert_eq!(parsed.runs, [run]);
+
+        let panicked = RunSummary {
+            return_values: vec!["1234".into()],
+            panicked: true,
+            panic_message: Some("invalid amount".into()),
+            steps: 5,
+            ..Default::default()
+        };
+        assert_eq!(panicked.to_string(), "Panicked with [invalid amount], 5 steps");
+
+        let metadata = RunMetadata {
+            package: Some("hello".into()),
+            version: Some("0.1.0".into()),
+            weight: Some("steps".into()),
+            timestamp: 1700000000,
+            ..Default::default()
+        };
+        assert_eq!(
+            metadata.comments(),
+            ["package: hello 0.1.0", "weight: steps", "timestamp: 1700000000"]
+        );
+        let report = report.with_metadata(Some(metadata.clone()));
+        let parsed: ProfileReport =
+            serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        assert_eq!(parsed.metadata, Some(metadata));
+    }
+
+    #[test]
+    fn test_delta_report() {
+        let report = DeltaReport::new(
+            "main;foo;store_temp 100\nmain;bar;jump 10",
+            "main;foo;store_temp 120\nmain;baz;jump 15",
+        );
+        assert_eq!(report.total.base, 110);
+        assert_eq!(report.total.current, 135);
+        let names: Vec<_> = report.functions.iter().map(|(name, _)| name.as_str()).collect();
+        // main changed by 25, foo by 20, baz by 15 and bar by 10
+        assert_eq!(names, ["main", "foo", "baz", "bar"]);
+        assert_eq!(report.functions[1].1.percent(), 20.0);
+        assert_eq!(report.functions[3].1.percent(), -100.0);
+    }
+
+    #[test]
+    fn test_category_report() {
+        let report = CategoryReport::new(
+            "main;foo;u32_overflowing_add 6\nmain;foo;store_temp<u32> 2\n\
+            main;foo;bar;pedersen 2\nmain;jump 1",
+        );
+        assert_eq!(report.functions["foo"]["arithmetic"], 6);
+        assert_eq!(report.functions["foo"]["memory"], 2);
+        assert_eq!(report.functions["foo"]["hashing"], 2);
+        assert_eq!(report.functions["main"]["control flow"], 1);
+    }
+
+    #[test]
+    fn test_path_report() {
+        let report = PathReport::new("main;foo;store_temp 10\nmain;bar;jump 12\nmain;foo;jump 5");
+        assert_eq!(report.total, 27);
+        assert_eq!(
+            report.paths,
+            vec![("main;foo".to_string(), 15), ("main;bar".to_string(), 12)]
+        );
+    }
+
+    #[cfg(feature = "runner")]
+    #[test]
+    fn test_syscall_sites() {
+        let stack = |frames: &[&str]| frames.iter().map(|frame| frame.to_string()).collect();
+        let member: Vec<String> = stack(&[
+            "token::MyToken::transfer",
+            "token::MyToken::__member_module_balances::StorageImpl::read",
+            "storage_read_syscall",
+        ]);
+        assert_eq!(storage_site(&member), "token::MyToken::balances");
+        let generic: Vec<String> = stack(&[
+            "token::MyToken::transfer",
+            "core::starknet::storage::StoragePointerReadAccess::<u256>::read",
+            "storage_read_syscall",
+        ]);
+        assert_eq!(storage_site(&generic), "token::MyToken::transfer");
+
+        let emit: Vec<String> = stack(&[
+            "token::MyToken::transfer",
+            "core::starknet::event::EventEmitter::emit::<token::MyToken::Transfer, Into>",
+            "emit_event_syscall",
+        ]);
+        assert_eq!(event_type(&emit), "token::MyToken::Transfer");
+    }
+
+    #[cfg(feature = "runner")]
+    #[test]
+    fn test_event_report() {
+        let (program, args) = profiler::falcon();
+        let mut profile = profiler::profile(program, "main", args, &Default::default()).unwrap();
+        profile.events = vec![profiler::EmittedEvent {
+            selector: Some(starknet_keccak(b"Transfer")),
+            keys: 3,
+            data: 2,
+        }];
+        let mut report = EventReport::default();
+        report.add(&profile);
+        let name = format!("selector {:#x}", starknet_keccak(b"Transfer"));
+        let stats = &report.events[&name];
+        assert_eq!((stats.emits, stats.keys, stats.data), (0, 3, 2));
+        assert_eq!(stats.l2_gas, 8 * CALLDATA_FELT_L2_GAS);
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::{bail, ensure, Context, Result};
+use num_bigint::BigUint;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// A contract call made by a transaction.
+pub struct Call {
+    /// Address of the called contract.
+    pub to: BigUint,
+    pub selector: BigUint,
+    pub calldata: Vec<BigUint>,
+}
+
+/// Fetch a transaction from a Starknet JSON-RPC node and decode the calls it makes.
+pub fn transaction_calls(rpc_url: &str, tx_hash: &str) -> Result<Vec<Call>> {
+    decode_calls(&fetch_transaction(rpc_url, tx_hash)?, tx_hash)
+}
+
+/// Execution resources of the receipt of a transaction, e.g. `steps` or `l2_gas` depending on
+/// the RPC version. Nested objects are flattened, e.g. `data_availability.l1_data_gas`.
+pub fn execution_resources(rpc_url: &str, tx_hash: &str) -> Result<BTreeMap<String, u64>> {
+    fn flatten(prefix: &str, value: &Value, resources: &mut BTreeMap<String, u64>) {
+        for (key, value) in value.as_object().into_iter().flatten() {
+            let key = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{prefix}.{key}")
+            };
+            match value {
+                Value::Object(_) => flatten(&key, value, resources),
+                _ => {
+                    if let Some(value) = value.as_u64() {
+                        resources.insert(key, value);
+                    }
+                }
+            }
+        }
+    }
+    let receipt = fetch_receipt(rpc_url, tx_hash)?;
+    let execution_resources = receipt
+        .get("execution_resources")
+        .with_context(|| format!("receipt of {tx_hash} has no execution resources"))?;
+    let mut resources = BTreeMap::new();
+    flatten("", execution_resources, &mut resources);
+    Ok(resources)
+}
+
+fn fetch_receipt(rpc_url: &str, tx_hash: &str) -> Result<Value> {
+    request(
+        rpc_url,
+        "starknet_getTransactionReceipt",
+        json!({ "transaction_hash": tx_hash }),
+    )
+    .with_context(|| format!("failed to fetch the receipt of {tx_hash} from {rpc_url}"))
+}
+
+fn fetch_transaction(rpc_url: &str, tx_hash: &str) -> Result<Value> {
+    request(
+        rpc_url,
+        "starknet_getTransactionByHash",
+        json!({ "transaction_hash": tx_hash }),
+    )
+    .with_context(|| format!("failed to fetch transaction {tx_hash} from {rpc_url}"))
+}
+
+fn decode_calls(tx: &Value, tx_hash: &str) -> Result<Vec<Call>> {
+    let calldata = tx
+        .get("calldata")
+        .and_then(Value::as_array)
+        .with_context(|| format!("transaction {tx_hash} has no calldata"))?
+        .iter()
+        .map(parse_felt)
+        .collect::<Result<Vec<_>>>()?;
+    match tx.get("type").and_then(Value::as_str) {
+        Some("INVOKE") => decode_multicall(calldata),
+        // L1 handlers call the entrypoint directly
+        Some("L1_HANDLER") => {
+            let selector = tx
+                .get("entry_point_selector")
+                .with_context(|| "L1 handler transaction has no entry point selector")?;
+            let to = tx
+                .get("contract_address")
+                .with_context(|| "L1 handler transaction has no contract address")?;
+            Ok(vec![Call {
+                to: parse_felt(to)?,
+                selector: parse_felt(selector)?,
+                calldata,
+            }])
+        }
+        ty => bail!("unsupported transaction type: {}", ty.unwrap_or("unknown")),
+    }
+}
+
+/// Result of a JSON-RPC request.
+fn request(rpc_url: &str, method: &str, params: Value) -> Result<Value> {
+    let response: Value = ureq::post(rpc_url)
+        .send_json(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        }))?
+        .into_json()
+        .with_context(|| "failed to decode RPC response")?;
+    if let Some(error) = response.get("error") {
+        bail!("RPC node returned an error: {error}");
+    }
+    response
+        .get("result")
+        .cloned()
+        .with_context(|| "RPC response has no result")
+}
+
+/// Decode the `__execute__` calldata of a Cairo 1 account:
+/// `[calls_len, (to, selector, calldata_len, ..calldata)*]`.
+pub fn decode_multicall(calldata: Vec<BigUint>) -> Result<Vec<Call>> {
+    let mut felts = calldata.into_iter();
+    let calls_len = felts
+        .next()
+        .with_context(|| "calldata is empty, expected the number of calls")?;
+    let calls_len =
+        usize::try_from(calls_len).with_context(|| "number of calls is out of range")?;
+
+    let mut calls = Vec::with_capacity(calls_len);
+    for _ in 0..calls_len {
+        let mut header = felts.by_ref().take(3);
+        let (Some(to), Some(selector), Some(len)) = (header.next(), header.next(), header.next())
+        else {
+            bail!("calldata is truncated, expected a call header");
+        };
+        let len = usize::try_from(len).with_context(|| "calldata length is out of range")?;
+        let calldata: Vec<_> = felts.by_ref().take(len).collect();
+        ensure!(
+            calldata.len() == len,
+            "calldata is truncated, expected {len} felts"
+        );
+        calls.push(Call {
+            to,
+            selector,
+            calldata,
+        });
+    }
+    ensure!(
+        felts.next().is_none(),
+        "unexpected trailing calldata, only Cairo 1 accounts are supported"
+    );
+    Ok(calls)
+}
+
+fn parse_felt(value: &Value) -> Result<BigUint> {
+    value
+        .as_str()
+        .and_then(|value| value.strip_prefix("0x"))
+        .and_then(|hex| BigUint::parse_bytes(hex.as_bytes(), 16))
+        .with_context(|| format!("invalid felt: {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_multicall() {
+        let calldata = [2, 0xa, 0x1, 2, 7, 8, 0xb, 0x2, 0]
+            .into_iter()
+            .map(BigUint::from)
+            .collect();
+        let calls = decode_multicall(calldata).unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].to, BigUint::from(0xau8));
+        assert_eq!(calls[0].selector, BigUint::from(1u8));
+        assert_eq!(
+            calls[0].calldata,
+            vec![BigUint::from(7u8), BigUint::from(8u8)]
+        );
+        assert!(calls[1].calldata.is_empty());
+
+        let truncated = [1, 0xa, 0x1, 3, 7].into_iter().map(BigUint::from).collect();
+        assert!(decode_multicall(truncated).is_err());
+    }
+}
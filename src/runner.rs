@@ -0,0 +1,119 @@
+use anyhow::Result;
+use cairo_lang_runner::Arg;
+use cairo_lang_sierra::program::Program;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::profiler::{self, Profile, ProfileOptions, Weight, WeightModel};
+use crate::report::{self, ProfileReport};
+
+/// Runs a function of a Sierra program and profiles it.
+pub struct Profiler {
+    program: Program,
+    function: String,
+    args: Vec<Arg>,
+    options: ProfileOptions,
+}
+
+impl Profiler {
+    /// Profiler of the `main` function without arguments, weighted by steps.
+    pub fn new(program: Program) -> Self {
+        Self {
+            program,
+            function: "main".into(),
+            args: Vec::new(),
+            options: ProfileOptions::default(),
+        }
+    }
+
+    /// Function to run, matched like `--function`.
+    pub fn with_function(self, function: impl Into<String>) -> Self {
+        Self {
+            function: function.into(),
+            ..self
+        }
+    }
+
+    pub fn with_args(self, args: Vec<Arg>) -> Self {
+        Self { args, ..self }
+    }
+
+    /// Weigh the frames by a built-in [`Weight`], steps by default, or by a custom model.
+    pub fn with_weight_model(mut self, model: impl WeightModel + 'static) -> Self {
+        match model.builtin() {
+            Some(weight) => {
+                self.options.weight = weight;
+                self.options.weight_model = None;
+            }
+            None => self.options.weight_model = Some(Arc::new(model)),
+        }
+        self
+    }
+
+    /// Settings of the run, they include the weight model.
+    pub fn with_options(self, options: ProfileOptions) -> Self {
+        Self { options, ..self }
+    }
+
+    /// Profile the function, see [`ProfileReport`].
+    pub fn run(self) -> Result<ProfileReport> {
+        let options = self.options.clone();
+        let profile = self.profile()?;
+        let folded = profiler::folded(&profile, &options);
+        let weight = match &options.weight_model {
+            Some(model) => model.name(),
+            None => options.weight.name(),
+        };
+        let builtins = profile
+            .resources
+            .basic_resources
+            .builtin_instance_counter
+            .iter()
+            .map(|(builtin, count)| (builtin.to_string(), *count))
+            .collect::<BTreeMap<_, _>>();
+        let runs = vec![report::RunSummary::new(&profile)];
+        Ok(ProfileReport::new(&folded, Some(weight), builtins).with_runs(runs))
+    }
+
+    /// Profile the function and pass the stacks with their weights to `on_stack` as they are
+    /// rendered instead of collecting them, e.g. to stream huge profiles to a server.
+    pub fn stream(self, on_stack: impl FnMut(&str, usize)) -> Result<Profile> {
+        let options = self.options.clone();
+        let profile = self.profile()?;
+        profiler::for_each_stack(&profile, &options, on_stack);
+        Ok(profile)
+    }
+
+    /// Run the function and return the raw profile, for the reports in [`report`].
+    pub fn profile(self) -> Result<Profile> {
+        profiler::profile(self.program, &self.function, self.args, &self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiler() {
+        let (program, args) = profiler::falcon();
+        let report = Profiler::new(program.clone())
+            .with_args(args.clone())
+            .with_weight_model(Weight::SierraGas)
+            .run()
+            .expect("failed to profile");
+        assert!(report.total > 0);
+        assert_eq!(report.total, report.tree.value);
+        assert_eq!(report.weight.as_deref(), Some("sierra-gas"));
+        assert!(!report.runs[0].panicked);
+        assert!(report.runs[0].remaining_gas.is_some());
+
+        let mut total = 0;
+        Profiler::new(program)
+            .with_args(args)
+            .with_weight_model(Weight::SierraGas)
+            .stream(|_, weight| total += weight)
+            .expect("failed to profile");
+        assert_eq!(total, report.total);
+    }
+}
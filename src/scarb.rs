@@ -0,0 +1,279 @@
+use anyhow::{bail, ensure, Context, Result};
+use camino::Utf8PathBuf;
+use scarb_metadata::{Metadata, MetadataCommand, PackageMetadata, ScarbCommand};
+use scarb_ui::args::PackagesFilter;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+
+/// Subset of the test target metadata written by Scarb next to the test Sierra file.
+#[derive(Deserialize)]
+struct TestMetadata {
+    named_tests: Vec<(String, serde_json::Value)>,
+}
+
+/// Subset of the artifacts index written by Scarb for a `starknet-contract` target.
+#[derive(Deserialize)]
+struct StarknetArtifacts {
+    contracts: Vec<ContractArtifacts>,
+}
+
+#[derive(Deserialize)]
+struct ContractArtifacts {
+    contract_name: String,
+    module_path: String,
+    artifacts: ContractArtifactPaths,
+}
+
+#[derive(Deserialize)]
+struct ContractArtifactPaths {
+    sierra: Utf8PathBuf,
+}
+
+/// Build options, forwarded to `scarb build`.
+#[derive(clap::Args, Clone, Debug)]
+pub struct BuildArgs {
+    /// Do not rebuild the package.
+    #[arg(long, default_value_t = false)]
+    pub no_build: bool,
+
+    /// Use the release profile, same as `--profile release`.
+    #[arg(long, default_value_t = false, conflicts_with = "profile")]
+    pub release: bool,
+
+    /// Profile to build with and to look the artifacts up in.
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Comma separated list of features to activate.
+    #[arg(long, value_delimiter = ',')]
+    pub features: Vec<String>,
+
+    /// Do not activate the `default` feature.
+    #[arg(long, default_value_t = false)]
+    pub no_default_features: bool,
+}
+
+impl BuildArgs {
+    fn profile(&self) -> Option<&str> {
+        if self.release {
+            Some("release")
+        } else {
+            self.profile.as_deref()
+        }
+    }
+}
+
+/// Read workspace metadata with the same profile as used for the build.
+pub fn metadata(build_args: &BuildArgs) -> Result<Metadata> {
+    let mut command = MetadataCommand::new();
+    command.inherit_stderr();
+    if let Some(profile) = build_args.profile() {
+        command.env("SCARB_PROFILE", profile);
+    }
+    Ok(command.exec()?)
+}
+
+/// Build targets of the given kind for a single package.
+pub fn build(package: &PackageMetadata, target_kind: &str, build_args: &BuildArgs) -> Result<()> {
+    if build_args.no_build {
+        return Ok(());
+    }
+
+    let filter = PackagesFilter::generate_for::<Metadata>(vec![package.clone()].iter());
+    let mut command = ScarbCommand::new();
+    command
+        .arg("build")
+        .env("SCARB_TARGET_KINDS", target_kind)
+        .env("SCARB_PACKAGES_FILTER", filter.to_env());
+    if let Some(profile) = build_args.profile() {
+        command.env("SCARB_PROFILE", profile);
+    }
+    if !build_args.features.is_empty() {
+        command.arg("--features").arg(build_args.features.join(","));
+    }
+    if build_args.no_default_features {
+        command.arg("--no-default-features");
+    }
+    command.run()?;
+    Ok(())
+}
+
+/// Find the target to build and the Sierra file it produces.
+/// Executable targets take precedence over the lib target.
+pub fn sierra_artifact(
+    metadata: &Metadata,
+    package: &PackageMetadata,
+) -> Result<(String, Utf8PathBuf)> {
+    let target = package
+        .targets
+        .iter()
+        .find(|target| target.kind == "executable")
+        .or_else(|| package.targets.iter().find(|target| target.kind == "lib"))
+        .with_context(|| {
+            format!(
+                "package `{}` has neither `[executable]` nor `[lib]` target",
+                package.name
+            )
+        })?;
+
+    let filename = match target.kind.as_str() {
+        "executable" => format!("{}.executable.sierra.json", target.name),
+        _ => format!("{}.sierra.json", target.name),
+    };
+    let path = artifacts_dir(metadata)?.join(filename);
+
+    Ok((target.kind.clone(), path))
+}
+
+/// Find a test matching the filter among the compiled test targets of a package.
+/// Returns the Sierra file of the test target and the full name of the test.
+pub fn find_test(
+    metadata: &Metadata,
+    package: &PackageMetadata,
+    filter: &str,
+) -> Result<(Utf8PathBuf, String)> {
+    let dir = artifacts_dir(metadata)?;
+    let mut candidates = Vec::new();
+    for target in package
+        .targets
+        .iter()
+        .filter(|target| target.kind == "test")
+    {
+        let path = dir.join(format!("{}.test.sierra.json", target.name));
+        ensure_compiled(&path, "test")?;
+
+        let metadata_path = dir.join(format!("{}.test.json", target.name));
+        let test_metadata: TestMetadata = serde_json::from_str(
+            &fs::read_to_string(&metadata_path)
+                .with_context(|| format!("failed to read test metadata: {metadata_path}"))?,
+        )
+        .with_context(|| format!("failed to deserialize test metadata: {metadata_path}"))?;
+
+        candidates.extend(
+            test_metadata
+                .named_tests
+                .into_iter()
+                .filter(|(name, _)| name.contains(filter))
+                .map(|(name, _)| (path.clone(), name)),
+        );
+    }
+
+    // Exact match wins over other tests containing the filter
+    if let Some(idx) = candidates.iter().position(|(_, name)| name == filter) {
+        return Ok(candidates.swap_remove(idx));
+    }
+    match candidates.len() {
+        0 => bail!("no test matches `{filter}` in package `{}`", package.name),
+        1 => Ok(candidates.remove(0)),
+        _ => bail!(
+            "multiple tests match `{filter}`, use the full test name:\n{}",
+            candidates
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
+}
+
+/// Find the contract class file of a contract compiled from the package.
+/// The contract name can be omitted if the package defines a single contract.
+pub fn contract_artifact(
+    metadata: &Metadata,
+    package: &PackageMetadata,
+    contract: Option<&str>,
+) -> Result<Utf8PathBuf> {
+    let dir = artifacts_dir(metadata)?;
+    let mut candidates = Vec::new();
+    for target in package
+        .targets
+        .iter()
+        .filter(|target| target.kind == "starknet-contract")
+    {
+        let path = dir.join(format!("{}.starknet_artifacts.json", target.name));
+        ensure_compiled(&path, "starknet-contract")?;
+
+        let artifacts: StarknetArtifacts = serde_json::from_str(
+            &fs::read_to_string(&path)
+                .with_context(|| format!("failed to read contract artifacts: {path}"))?,
+        )
+        .with_context(|| format!("failed to deserialize contract artifacts: {path}"))?;
+
+        candidates.extend(
+            artifacts
+                .contracts
+                .into_iter()
+                .filter(|artifact| {
+                    contract.is_none_or(|name| {
+                        artifact.contract_name == name || artifact.module_path == name
+                    })
+                })
+                .map(|artifact| (artifact.module_path, dir.join(artifact.artifacts.sierra))),
+        );
+    }
+
+    match candidates.len() {
+        0 => bail!("no matching contract found in package `{}`", package.name),
+        1 => Ok(candidates.remove(0).1),
+        _ => bail!(
+            "multiple contracts found, specify one with `--contract`:\n{}",
+            candidates
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
+}
+
+/// Arguments preset from `[tool.burn.scenarios]` in the package manifest.
+pub fn scenario(package: &PackageMetadata, name: &str) -> Result<serde_json::Value> {
+    let scenarios = package
+        .tool_metadata("burn")
+        .and_then(|tool| tool.get("scenarios"))
+        .and_then(|scenarios| scenarios.as_object())
+        .with_context(|| {
+            format!(
+                "package {} has no `[tool.burn.scenarios]` in Scarb.toml",
+                package.name
+            )
+        })?;
+    match scenarios.get(name) {
+        Some(scenario) => Ok(scenario.clone()),
+        None => bail!(
+            "scenario `{name}` not found in package {}, available scenarios: {}",
+            package.name,
+            scenarios.keys().cloned().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// Directory containing artifacts compiled with the current profile.
+fn artifacts_dir(metadata: &Metadata) -> Result<Utf8PathBuf> {
+    let target_dir = match &metadata.target_dir {
+        Some(target_dir) => target_dir.clone(),
+        None => Utf8PathBuf::from(env::var("SCARB_TARGET_DIR")?),
+    };
+    Ok(target_dir.join(&metadata.current_profile))
+}
+
+/// Check that the artifact of the given target kind has been built.
+pub fn ensure_compiled(path: &Utf8PathBuf, target_kind: &str) -> Result<()> {
+    let hint = match target_kind {
+        "executable" => "make sure you have `sierra = true` in `[executable]` target in Scarb.toml",
+        "test" => "make sure you have `cairo_test` in `[dev-dependencies]` in Scarb.toml",
+        "starknet-contract" => "make sure you have `[[target.starknet-contract]]` in Scarb.toml",
+        _ => "make sure you have `[lib]` target in Scarb.toml",
+    };
+    ensure!(
+        path.exists(),
+        format!(
+            r#"
+            Package has not been compiled, file does not exist: {path}
+            {hint}
+        "#
+        )
+    );
+    Ok(())
+}
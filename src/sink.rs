@@ -0,0 +1,450 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use inferno::flamegraph::{from_lines, Direction, Options};
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Command;
+
+use crate::burnprofile::{self, BurnProfile};
+use crate::listing::{self, StatementWeights};
+use crate::pprof_profile::{self, SampleType};
+use crate::report::{ProfileReport, RunMetadata, RunSummary};
+use crate::source::SourceMap;
+use crate::{
+    callgrind, chrome_trace, dot, gecko, html, lcov, outln, output, snforge, speedscope, table,
+    viewer,
+};
+
+/// Profile handed to the sinks, the folded stacks and what some of the formats need on top.
+#[derive(Default)]
+pub struct SinkProfile<'a> {
+    /// Stacks in the folded format, `a;b;c 123` per line.
+    pub folded: &'a str,
+    /// Name of the weight model of the stacks, e.g. `steps`, `None` if unknown.
+    pub weight: Option<String>,
+    /// Stacks weighted by the pprof sample types, pprof falls back to `folded` if empty.
+    pub sample_types: Vec<SampleType<'a>>,
+    /// Builtin usages counted by the VM.
+    pub builtins: BTreeMap<String, usize>,
+    pub locations: Option<&'a SourceMap>,
+    /// Weights of the Sierra statements, required by the listings and the coverage.
+    pub statements: Option<&'a StatementWeights>,
+    pub program_hash: Option<String>,
+    pub arguments_hash: Option<String>,
+    /// Return values and resources of the runs, included in the report.
+    pub runs: &'a [RunSummary],
+    /// Origin of the profile, written in the report and the comments of the formats having them.
+    pub metadata: Option<RunMetadata>,
+}
+
+impl<'a> SinkProfile<'a> {
+    pub fn new(folded: &'a str) -> Self {
+        Self {
+            folded,
+            ..Default::default()
+        }
+    }
+
+    pub fn report(&self) -> ProfileReport {
+        ProfileReport::new(self.folded, self.weight.clone(), self.builtins.clone())
+            .with_runs(self.runs.to_vec())
+            .with_metadata(self.metadata.clone())
+    }
+
+    pub fn burnprofile(&self) -> BurnProfile {
+        BurnProfile::new(
+            self.folded,
+            self.program_hash.clone(),
+            self.arguments_hash.clone(),
+            self.weight.clone(),
+        )
+    }
+
+    /// Comment lines of the metadata, empty if unknown.
+    fn comments(&self) -> Vec<String> {
+        self.metadata.as_ref().map(RunMetadata::comments).unwrap_or_default()
+    }
+
+    fn statements(&self, message: &'static str) -> Result<&'a StatementWeights> {
+        self.statements.with_context(|| message)
+    }
+}
+
+/// Destination of a profile, e.g. a file in one of the output formats.
+pub trait ProfileSink {
+    /// What the sink writes, used in messages, e.g. `Flamegraph`.
+    fn name(&self) -> &str;
+
+    /// Write the profile to the path, `-` is the standard output.
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()>;
+
+    /// Open the written profile for viewing, nothing is opened by default.
+    fn open(&self, _profile: &SinkProfile, _path: &Utf8PathBuf) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Write the profile to the sink and report it, then open it if requested.
+pub fn write(
+    sink: &dyn ProfileSink,
+    profile: &SinkProfile,
+    path: &Utf8PathBuf,
+    open: bool,
+) -> Result<()> {
+    sink.write(profile, path)?;
+    output::written(sink.name(), path);
+    if open {
+        sink.open(profile, path)?;
+    }
+    Ok(())
+}
+
+fn open_file(path: &Utf8PathBuf) -> Result<()> {
+    let absolute_path = fs::canonicalize(path)?;
+    webbrowser::open(&format!("file://{}", absolute_path.display()))?;
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Flamegraph {
+    pub inverted: bool,
+    pub flamechart: bool,
+    pub icicle: bool,
+}
+
+impl ProfileSink for Flamegraph {
+    fn name(&self) -> &str {
+        "Flamegraph"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        let mut opt = Options::default();
+        opt.reverse_stack_order = self.inverted;
+        opt.flame_chart = self.flamechart;
+        if self.icicle {
+            opt.direction = Direction::Inverted;
+        }
+        let mut svg = Vec::new();
+        from_lines(&mut opt, profile.folded.lines(), &mut svg)
+            .with_context(|| "failed to write flamegraph")?;
+        let svg = with_comments(String::from_utf8(svg)?, &profile.comments());
+        output::write(path, svg).with_context(|| format!("failed to write flamegraph: {path}"))
+    }
+
+    fn open(&self, _profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        open_file(path)
+    }
+}
+
+/// Put the lines in an XML comment before the root element of the SVG.
+fn with_comments(svg: String, comments: &[String]) -> String {
+    let Some(start) = svg.find("<svg") else {
+        return svg;
+    };
+    if comments.is_empty() {
+        return svg;
+    }
+    // Comments can't contain `--`
+    let comment = comments.join("\n").replace("--", "- -");
+    format!("{}<!--\n{comment}\n-->\n{}", &svg[..start], &svg[start..])
+}
+
+pub struct Pprof;
+
+impl ProfileSink for Pprof {
+    fn name(&self) -> &str {
+        "Profile file"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        let primary = [SampleType {
+            name: "weight",
+            unit: "count",
+            folded: profile.folded,
+        }];
+        let sample_types = if profile.sample_types.is_empty() {
+            &primary[..]
+        } else {
+            &profile.sample_types[..]
+        };
+        let empty = SourceMap::default();
+        let locations = profile.locations.unwrap_or(&empty);
+        pprof_profile::write(sample_types, &locations.functions, &profile.comments(), path)
+    }
+
+    fn open(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        let has_go = Command::new("go")
+            .arg("version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if has_go {
+            Command::new("go")
+                .args(["tool", "pprof", "-http=:8000", path.as_str()])
+                .status()
+                .with_context(|| "failed to start pprof server")?;
+        } else {
+            outln!("Go toolchain not found, using the built-in viewer");
+            let title = path.file_stem().unwrap_or("scarb-burn");
+            viewer::serve(profile.folded, title, 8000)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Speedscope;
+
+impl ProfileSink for Speedscope {
+    fn name(&self) -> &str {
+        "Speedscope profile"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        speedscope::write(profile.folded, path)
+    }
+
+    fn open(&self, _profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        webbrowser::open("https://www.speedscope.app")?;
+        outln!("Drop {path} into speedscope to open it");
+        Ok(())
+    }
+}
+
+pub struct ChromeTrace;
+
+impl ProfileSink for ChromeTrace {
+    fn name(&self) -> &str {
+        "Trace"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        chrome_trace::write(profile.folded, path)
+    }
+
+    fn open(&self, _profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        webbrowser::open("https://ui.perfetto.dev")?;
+        outln!("Open {path} in Perfetto to view it");
+        Ok(())
+    }
+}
+
+pub struct Callgrind;
+
+impl ProfileSink for Callgrind {
+    fn name(&self) -> &str {
+        "Callgrind file"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        callgrind::write(profile.folded, path)
+    }
+
+    fn open(&self, _profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        Command::new("kcachegrind")
+            .arg(path.as_str())
+            .status()
+            .with_context(|| "failed to start kcachegrind")?;
+        Ok(())
+    }
+}
+
+pub struct Html;
+
+impl ProfileSink for Html {
+    fn name(&self) -> &str {
+        "Flamegraph"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        html::write(profile.folded, path)
+    }
+
+    fn open(&self, _profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        open_file(path)
+    }
+}
+
+pub struct Folded;
+
+impl ProfileSink for Folded {
+    fn name(&self) -> &str {
+        "Folded stacks"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        let mut folded = profile.folded.trim_end().to_string();
+        folded.push('\n');
+        output::write(path, folded)
+            .with_context(|| format!("failed to write folded stacks: {path}"))
+    }
+}
+
+pub struct Burnprofile;
+
+impl ProfileSink for Burnprofile {
+    fn name(&self) -> &str {
+        "Profile"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        burnprofile::write(&profile.burnprofile(), path)
+    }
+}
+
+/// Function table with the given delimiter, e.g. `,` for CSV.
+pub struct Table(pub char);
+
+impl ProfileSink for Table {
+    fn name(&self) -> &str {
+        "Function table"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        table::write(profile.folded, path, self.0)
+    }
+}
+
+pub struct Json;
+
+impl ProfileSink for Json {
+    fn name(&self) -> &str {
+        "JSON profile"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        output::write(path, serde_json::to_string_pretty(&profile.report())?)
+            .with_context(|| format!("failed to write JSON profile: {path}"))
+    }
+}
+
+pub struct Firefox;
+
+impl ProfileSink for Firefox {
+    fn name(&self) -> &str {
+        "Firefox profile"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        gecko::write(profile.folded, path)
+    }
+
+    fn open(&self, _profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        webbrowser::open("https://profiler.firefox.com/from-file/")?;
+        outln!("Load {path} in the Firefox Profiler to open it");
+        Ok(())
+    }
+}
+
+pub struct SierraListing;
+
+impl ProfileSink for SierraListing {
+    fn name(&self) -> &str {
+        "Annotated Sierra listing"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        let statements = profile
+            .statements("the annotated Sierra listing requires running a Sierra program")?;
+        listing::write_sierra(statements, path)
+    }
+}
+
+pub struct CasmListing;
+
+impl ProfileSink for CasmListing {
+    fn name(&self) -> &str {
+        "Annotated CASM listing"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        let statements = profile
+            .statements("the annotated CASM listing requires running a Sierra program")?;
+        listing::write_casm(statements, path)
+    }
+}
+
+pub struct SourceListing;
+
+impl ProfileSink for SourceListing {
+    fn name(&self) -> &str {
+        "Annotated sources"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        let statements = profile
+            .statements("the annotated sources require running a Sierra program")?;
+        let empty = SourceMap::default();
+        listing::write_source(statements, profile.locations.unwrap_or(&empty), path)
+    }
+}
+
+pub struct Lcov;
+
+impl ProfileSink for Lcov {
+    fn name(&self) -> &str {
+        "Coverage"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        let statements = profile.statements("the coverage requires running a Sierra program")?;
+        let empty = SourceMap::default();
+        lcov::write(statements, profile.locations.unwrap_or(&empty), path)
+    }
+}
+
+/// Call trace in the snforge format, read by cairo-profiler.
+pub struct SnforgeTrace;
+
+impl ProfileSink for SnforgeTrace {
+    fn name(&self) -> &str {
+        "Call trace"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        snforge::write(profile.folded, profile.weight.as_deref(), path)
+    }
+}
+
+pub struct Dot;
+
+impl ProfileSink for Dot {
+    fn name(&self) -> &str {
+        "Call graph"
+    }
+
+    fn write(&self, profile: &SinkProfile, path: &Utf8PathBuf) -> Result<()> {
+        dot::write(profile.folded, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Sink collecting the written stacks.
+    #[derive(Default)]
+    struct Collect(RefCell<String>);
+
+    impl ProfileSink for Collect {
+        fn name(&self) -> &str {
+            "Stacks"
+        }
+
+        fn write(&self, profile: &SinkProfile, _path: &Utf8PathBuf) -> Result<()> {
+            self.0.borrow_mut().push_str(profile.folded);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sink() {
+        let sink = Collect::default();
+        let profile = SinkProfile::new("main;foo 2\n");
+        write(&sink, &profile, &"-".into(), false).unwrap();
+        assert_eq!(*sink.0.borrow(), "main;foo 2\n");
+        assert!(SierraListing.write(&profile, &"-".into()).is_err());
+
+        let svg = with_comments("<?xml?><svg></svg>".into(), &["weight: steps".into()]);
+        assert_eq!(svg, "<?xml?><!--\nweight: steps\n-->\n<svg></svg>");
+    }
+}
@@ -0,0 +1,207 @@
+//! Call traces saved by Starknet Foundry with `snforge test --save-trace-data`, a tree of the
+//! contract calls of a test with the resources used by each call including its nested calls.
+//! It is also the input format of cairo-profiler.
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::burnprofile::Node;
+use crate::output;
+
+#[derive(Deserialize)]
+struct CallTrace {
+    entry_point: EntryPoint,
+    cumulative_resources: Resources,
+    /// Either `{"EntryPointCall": <trace>}` or `"DeployWithoutConstructor"`.
+    #[serde(default)]
+    nested_calls: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct EntryPoint {
+    contract_name: Option<String>,
+    function_name: Option<String>,
+    contract_address: Option<Value>,
+    entry_point_selector: Option<Value>,
+}
+
+#[derive(Default, Deserialize)]
+struct Resources {
+    #[serde(default)]
+    vm_resources: VmResources,
+    gas_consumed: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+struct VmResources {
+    #[serde(default)]
+    n_steps: usize,
+}
+
+/// Whether the contents look like an snforge trace rather than another JSON profile.
+pub fn is_trace(contents: &str) -> bool {
+    serde_json::from_str::<Value>(contents).is_ok_and(|value| trace_value(value).is_some())
+}
+
+/// The trace itself, unwrapping the version of versioned traces.
+fn trace_value(value: Value) -> Option<Value> {
+    let value = match value.get("V1") {
+        Some(trace) => trace.clone(),
+        None => value,
+    };
+    value.get("entry_point").is_some().then_some(value)
+}
+
+/// Folded stacks of the calls weighted by steps, or by Sierra gas if the trace has no steps,
+/// e.g. for tests run with the Sierra gas tracking. The weight of a call excludes the weight
+/// of its nested calls.
+pub fn folded(contents: &str) -> Result<String> {
+    let value = trace_value(serde_json::from_str(contents)?).with_context(|| "not a call trace")?;
+    let trace: CallTrace = serde_json::from_value(value)?;
+    let by_steps = trace.cumulative_resources.vm_resources.n_steps > 0;
+    let mut output = String::new();
+    visit(&trace, by_steps, &mut Vec::new(), &mut output)?;
+    Ok(output)
+}
+
+fn visit(
+    trace: &CallTrace,
+    by_steps: bool,
+    stack: &mut Vec<String>,
+    output: &mut String,
+) -> Result<()> {
+    let weight = |trace: &CallTrace| {
+        let resources = &trace.cumulative_resources;
+        if by_steps {
+            resources.vm_resources.n_steps
+        } else {
+            resources.gas_consumed.unwrap_or_default()
+        }
+    };
+    let nested = trace
+        .nested_calls
+        .iter()
+        .filter_map(|call| call.get("EntryPointCall"))
+        .map(|call| serde_json::from_value::<CallTrace>(call.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    stack.push(frame(&trace.entry_point));
+    let own = weight(trace).saturating_sub(nested.iter().map(weight).sum());
+    if own > 0 {
+        output.push_str(&format!("{} {own}\n", stack.join(";")));
+    }
+    for call in &nested {
+        visit(call, by_steps, stack, output)?;
+    }
+    stack.pop();
+    Ok(())
+}
+
+/// Trace of the profile with a call per frame, read by cairo-profiler and back by scarb-burn.
+/// The weights are Sierra gas for the gas models and steps otherwise.
+pub fn trace(folded: &str, weight: Option<&str>) -> Value {
+    let by_gas = matches!(weight, Some("sierra-gas" | "l2-gas"));
+    fn call(node: &Node, by_gas: bool) -> Value {
+        let (contract, function) = match node.name.rsplit_once("::") {
+            Some((contract, function)) => (Some(contract), function),
+            None => (None, node.name.as_str()),
+        };
+        let (steps, gas) = if by_gas {
+            (0, Some(node.value))
+        } else {
+            (node.value, None)
+        };
+        json!({
+            "entry_point": {
+                "class_hash": "0x0",
+                "entry_point_type": "External",
+                "entry_point_selector": "0x0",
+                "contract_address": "0x0",
+                "call_type": "Call",
+                "contract_name": contract,
+                "function_name": function,
+            },
+            "cumulative_resources": {
+                "vm_resources": {
+                    "n_steps": steps,
+                    "n_memory_holes": 0,
+                    "builtin_instance_counter": {},
+                },
+                "gas_consumed": gas,
+            },
+            "used_l1_resources": { "l2_l1_message_sizes": [] },
+            "nested_calls": node
+                .children
+                .iter()
+                .map(|child| json!({ "EntryPointCall": call(child, by_gas) }))
+                .collect::<Vec<_>>(),
+            "cairo_execution_info": null,
+        })
+    }
+    let root = Node::from_folded(folded);
+    // A single entrypoint is the root call itself, like the test of an snforge trace
+    let root = match root.children.as_slice() {
+        [child] => call(child, by_gas),
+        _ => call(&root, by_gas),
+    };
+    json!({ "V1": root })
+}
+
+pub fn write(folded: &str, weight: Option<&str>, path: &Utf8PathBuf) -> Result<()> {
+    output::write(path, serde_json::to_string(&trace(folded, weight))?)
+        .with_context(|| format!("failed to write snforge trace: {path}"))
+}
+
+/// `Contract::function` if the names are known, the address and selector otherwise. Frames
+/// written without a contract, e.g. libfuncs, are read back as the function alone.
+fn frame(entry_point: &EntryPoint) -> String {
+    let felt = |value: &Option<Value>| match value {
+        Some(Value::String(felt)) => felt.clone(),
+        Some(value) => value.to_string(),
+        None => "unknown".into(),
+    };
+    match (&entry_point.contract_name, &entry_point.function_name) {
+        (Some(contract), Some(function)) => format!("{contract}::{function}"),
+        (None, Some(function)) => function.clone(),
+        (contract, None) => format!(
+            "{}::{}",
+            contract
+                .clone()
+                .unwrap_or_else(|| felt(&entry_point.contract_address)),
+            felt(&entry_point.entry_point_selector)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snforge_trace() {
+        let saved = r#"{"V1": {
+            "entry_point": {"contract_name": "SNFORGE_TEST_CODE", "function_name": "test_swap"},
+            "cumulative_resources": {"vm_resources": {"n_steps": 100}},
+            "nested_calls": [
+                {"EntryPointCall": {
+                    "entry_point": {"contract_address": "0x12", "entry_point_selector": "0x34"},
+                    "cumulative_resources": {"vm_resources": {"n_steps": 60}},
+                    "nested_calls": ["DeployWithoutConstructor"]
+                }}
+            ]
+        }}"#;
+        assert!(is_trace(saved));
+        assert!(!is_trace(r#"{"format": "burnprofile"}"#));
+        assert_eq!(
+            folded(saved).unwrap(),
+            "SNFORGE_TEST_CODE::test_swap 40\nSNFORGE_TEST_CODE::test_swap;0x12::0x34 60\n"
+        );
+
+        let stacks = "pkg::main 2\npkg::main;pkg::foo;store_temp 10\n";
+        assert_eq!(folded(&trace(stacks, Some("steps")).to_string()).unwrap(), stacks);
+        let by_gas = trace(stacks, Some("sierra-gas")).to_string();
+        assert_eq!(folded(&by_gas).unwrap(), stacks);
+    }
+}
@@ -0,0 +1,100 @@
+use cairo_lang_sierra::debug_info::DebugInfo;
+use cairo_lang_sierra::program::Program;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::folded;
+
+/// Annotations Scarb adds to Sierra files with
+/// `unstable-add-statements-code-locations-debug-info = true`.
+const CODE_LOCATIONS_NAMESPACE: &str = "github.com/software-mansion/cairo-coverage";
+
+/// Position in the Cairo sources.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub file: String,
+    /// One-based line number.
+    pub line: u32,
+}
+
+/// Source locations of a program, empty if it has no code locations.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    /// Locations of the user functions by their path, taken from the first statement of
+    /// every function having a code location.
+    pub functions: HashMap<String, Location>,
+    /// Locations of the statements by their index.
+    pub statements: HashMap<usize, Location>,
+}
+
+pub fn source_map(program: &Program, debug_info: Option<&DebugInfo>) -> SourceMap {
+    let Some(code_locations) = debug_info
+        .and_then(|debug_info| debug_info.annotations.get(CODE_LOCATIONS_NAMESPACE))
+        .and_then(|annotation| annotation.get("statements_code_locations"))
+        .and_then(Value::as_object)
+    else {
+        return SourceMap::default();
+    };
+    let statements: HashMap<usize, Location> = code_locations
+        .iter()
+        .filter_map(|(idx, locations)| Some((idx.parse().ok()?, statement_location(locations)?)))
+        .collect();
+
+    let mut entry_points: Vec<_> = program
+        .funcs
+        .iter()
+        .filter_map(|func| Some((func.entry_point.0, func.id.debug_name.as_ref()?.as_str())))
+        .collect();
+    entry_points.sort();
+
+    let mut functions = HashMap::new();
+    for (idx, &(entry_point, name)) in entry_points.iter().enumerate() {
+        let end = entry_points
+            .get(idx + 1)
+            .map_or(program.statements.len(), |(next, _)| *next);
+        if let Some(location) = (entry_point..end).find_map(|idx| statements.get(&idx)) {
+            let stripped = folded::strip_generics(name);
+            if stripped != name {
+                functions.entry(stripped).or_insert_with(|| location.clone());
+            }
+            functions.insert(name.to_string(), location.clone());
+        }
+    }
+    SourceMap {
+        functions,
+        statements,
+    }
+}
+
+/// Location of the code the statement was generated from, ignoring the code it was inlined into.
+fn statement_location(locations: &Value) -> Option<Location> {
+    let location = locations.as_array()?.first()?.as_array()?;
+    let file = location.first()?.as_str()?;
+    let line = location.get(1)?.get("start")?.get("line")?.as_u64()?;
+    Some(Location {
+        file: file.to_string(),
+        line: line as u32 + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_statement_location() {
+        let locations = json!([
+            ["/src/lib.cairo", {"start": {"line": 9, "col": 4}, "end": {"line": 9, "col": 12}}],
+            ["/src/main.cairo", {"start": {"line": 2, "col": 0}, "end": {"line": 4, "col": 1}}],
+        ]);
+        assert_eq!(
+            statement_location(&locations),
+            Some(Location {
+                file: "/src/lib.cairo".into(),
+                line: 10,
+            })
+        );
+        assert_eq!(statement_location(&json!([])), None);
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::folded;
+use crate::output;
+
+/// Convert a folded profile to a sampled profile of the speedscope file format,
+/// one sample per stack weighted by its weight.
+pub fn profile(folded: &str, name: &str) -> Value {
+    let mut frames: Vec<&str> = Vec::new();
+    let mut frame_indices: HashMap<&str, usize> = HashMap::new();
+    let mut samples = Vec::new();
+    let mut weights = Vec::new();
+    for (stack, weight) in folded::lines(folded) {
+        let sample: Vec<usize> = stack
+            .split(';')
+            .map(|frame| {
+                *frame_indices.entry(frame).or_insert_with(|| {
+                    frames.push(frame);
+                    frames.len() - 1
+                })
+            })
+            .collect();
+        samples.push(sample);
+        weights.push(weight);
+    }
+    let total: usize = weights.iter().sum();
+    json!({
+        "$schema": "https://www.speedscope.app/file-format-schema.json",
+        "shared": {
+            "frames": frames.iter().map(|name| json!({ "name": name })).collect::<Vec<_>>(),
+        },
+        "profiles": [{
+            "type": "sampled",
+            "name": name,
+            "unit": "none",
+            "startValue": 0,
+            "endValue": total,
+            "samples": samples,
+            "weights": weights,
+        }],
+        "name": name,
+        "activeProfileIndex": 0,
+        "exporter": concat!("scarb-burn ", env!("CARGO_PKG_VERSION")),
+    })
+}
+
+/// Write a folded profile as a speedscope JSON file.
+pub fn write(folded: &str, path: &Utf8PathBuf) -> Result<()> {
+    let name = path.file_stem().unwrap_or("scarb-burn");
+    output::write(path, serde_json::to_string(&profile(folded, name))?)
+        .with_context(|| format!("failed to write speedscope profile: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let profile = profile("main;foo 10\nmain;bar 5\nmain;foo;baz 1", "test");
+        assert_eq!(
+            profile["shared"]["frames"],
+            json!([{ "name": "main" }, { "name": "foo" }, { "name": "bar" }, { "name": "baz" }])
+        );
+        assert_eq!(
+            profile["profiles"][0]["samples"],
+            json!([[0, 1], [0, 2], [0, 1, 3]])
+        );
+        assert_eq!(profile["profiles"][0]["weights"], json!([10, 5, 1]));
+        assert_eq!(profile["profiles"][0]["endValue"], 16);
+    }
+}
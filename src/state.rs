@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use cairo_lang_runner::StarknetState;
+use camino::Utf8PathBuf;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use std::fs;
+
+use crate::arguments;
+
+/// Starknet state the programs run against, loaded from a TOML fixture, e.g.
+/// `caller_address = "0x123"`. Felts are hex, decimal or short strings in single quotes.
+/// The runner only lets the addresses of the execution info be set.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StateFixture {
+    pub caller_address: Option<String>,
+    pub contract_address: Option<String>,
+}
+
+impl StateFixture {
+    pub fn load(path: &Utf8PathBuf) -> Result<Self> {
+        let fixture: Self = toml::from_str(
+            &fs::read_to_string(path)
+                .with_context(|| format!("failed to read Starknet state: {path}"))?,
+        )
+        .with_context(|| format!("failed to parse Starknet state: {path}"))?;
+        // Fail early on invalid felts rather than when running
+        fixture
+            .starknet_state()
+            .with_context(|| format!("invalid Starknet state: {path}"))?;
+        Ok(fixture)
+    }
+
+    /// State of the runner with the addresses of the fixture.
+    pub fn starknet_state(&self) -> Result<StarknetState> {
+        let mut state = StarknetState::default();
+        let address = |value: &Option<String>| value.as_deref().map(parse_address).transpose();
+        let caller_address = address(&self.caller_address)?.unwrap_or_default();
+        let contract_address = address(&self.contract_address)?.unwrap_or_default();
+        state.open_caller_context((contract_address.into(), caller_address.into()));
+        Ok(state)
+    }
+}
+
+fn parse_address(value: &str) -> Result<BigUint> {
+    arguments::parse_felt_literal(value)?
+        .to_biguint()
+        .with_context(|| format!("negative address `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_fixture() {
+        assert_eq!(parse_address("0x10").unwrap(), BigUint::from(16u8));
+        assert_eq!(parse_address("16").unwrap(), BigUint::from(16u8));
+        assert_eq!(parse_address("'AB'").unwrap(), BigUint::from(0x4142u16));
+        assert!(parse_address("0xzz").is_err());
+        assert!(parse_address("-1").is_err());
+
+        let fixture: StateFixture = toml::from_str("caller_address = '0x123'").unwrap();
+        assert!(fixture.starknet_state().is_ok());
+        let fixture: StateFixture =
+            toml::from_str("caller_address = \"'OWNER'\"").unwrap();
+        assert!(fixture.starknet_state().is_ok());
+        let fixture: StateFixture = toml::from_str("contract_address = '0x456'").unwrap();
+        assert!(fixture.starknet_state().is_ok());
+    }
+}
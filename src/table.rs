@@ -0,0 +1,71 @@
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+
+use crate::folded;
+use crate::output;
+use crate::report::FunctionReport;
+
+/// Render the weights per function of a folded profile as a delimited table, sorted by
+/// inclusive weight. Calls are left empty for functions without `function_call` leaves,
+/// e.g. the entrypoint.
+pub fn table(folded: &str, delimiter: char) -> String {
+    let calls = folded::calls_per_function(folded);
+    let report = FunctionReport::new(folded);
+    let mut functions: Vec<_> = report.functions.iter().collect();
+    functions.sort_by(|a, b| b.1.inclusive.cmp(&a.1.inclusive).then(a.0.cmp(b.0)));
+
+    let header = ["function", "inclusive", "exclusive", "calls"];
+    let mut output = header.join(&delimiter.to_string());
+    output.push('\n');
+    for (function, weights) in functions {
+        let row = [
+            quote(function, delimiter),
+            weights.inclusive.to_string(),
+            weights.exclusive.to_string(),
+            calls
+                .get(function.as_str())
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+        ];
+        output.push_str(&row.join(&delimiter.to_string()));
+        output.push('\n');
+    }
+    output
+}
+
+/// Quote a field containing the delimiter or quotes, e.g. a generic function path.
+fn quote(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Write the weights per function of a folded profile as CSV or TSV depending on the delimiter.
+pub fn write(folded: &str, path: &Utf8PathBuf, delimiter: char) -> Result<()> {
+    output::write(path, table(folded, delimiter))
+        .with_context(|| format!("failed to write table: {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let folded = "main;foo<(u8, u8)>;store_temp 10\nmain;function_call<user@foo<(u8, u8)>> 2";
+        assert_eq!(
+            table(folded, ','),
+            "function,inclusive,exclusive,calls\n\
+            main,12,2,\n\
+            \"foo<(u8, u8)>\",10,10,2\n"
+        );
+        assert_eq!(
+            table(folded, '\t'),
+            "function\tinclusive\texclusive\tcalls\n\
+            main\t12\t2\t\n\
+            foo<(u8, u8)>\t10\t10\t2\n"
+        );
+    }
+}
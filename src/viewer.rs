@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::dot;
+use crate::html;
+use crate::outln;
+use crate::report::FunctionReport;
+
+/// Navigation between the views, put at the top of every page.
+const NAV: &str = r#"<nav><a href="/flamegraph">Flame graph</a> | <a href="/top">Top</a> | <a href="/graph">Graph</a></nav>"#;
+
+const STYLE: &str = r#"<style>
+body { font-family: sans-serif; margin: 16px; }
+table { border-collapse: collapse; font-size: 13px; }
+th, td { padding: 2px 8px; text-align: right; }
+td:last-child, th:last-child { text-align: left; font-family: monospace; }
+tr:nth-child(even) { background: #f4f4f4; }
+</style>"#;
+
+/// Serve the flame graph, top functions and call graph views of a folded profile on
+/// localhost, like `go tool pprof -http` does, until the process is interrupted.
+pub fn serve(folded: &str, title: &str, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .with_context(|| format!("failed to listen on port {port}"))?;
+    let url = format!("http://localhost:{port}");
+    outln!("Serving the profile at {url}, press Ctrl+C to stop");
+    webbrowser::open(&url)?;
+    for stream in listener.incoming() {
+        let result = stream
+            .map_err(anyhow::Error::from)
+            .and_then(|stream| handle(stream, folded, title));
+        if let Err(err) = result {
+            eprintln!("Failed to handle a request: {err:#}");
+        }
+    }
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, folded: &str, title: &str) -> Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    // e.g. `GET /top HTTP/1.1`
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let response = match page(path, folded, title)? {
+        Some(body) => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        ),
+        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".into(),
+    };
+    stream.write_all(response.as_bytes())?;
+    Ok(())
+}
+
+/// Page of the given path, `None` if there is no such page.
+fn page(path: &str, folded: &str, title: &str) -> Result<Option<String>> {
+    let page = match path.split('?').next().unwrap_or(path) {
+        "/" | "/flamegraph" => {
+            html::page(folded, title)?.replacen("<body>", &format!("<body>\n{NAV}"), 1)
+        }
+        "/top" => top(folded, title),
+        "/graph" => graph(folded, title)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(page))
+}
+
+/// Functions sorted by self weight, with the columns of `pprof -top`.
+fn top(folded: &str, title: &str) -> String {
+    let report = FunctionReport::new(folded);
+    let total = report.total.max(1) as f64;
+    let mut functions: Vec<_> = report.functions.iter().collect();
+    functions.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(b.0)));
+
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n{STYLE}\n</head>\n<body>\n{NAV}\n<table>\n<tr><th>flat</th><th>flat%</th><th>sum%</th><th>cum</th><th>cum%</th><th>function</th></tr>\n",
+        html::escape(title)
+    );
+    let mut sum = 0;
+    for (name, weights) in functions {
+        sum += weights.exclusive;
+        let _ = writeln!(
+            page,
+            "<tr><td>{}</td><td>{:.2}%</td><td>{:.2}%</td><td>{}</td><td>{:.2}%</td><td>{}</td></tr>",
+            weights.exclusive,
+            100.0 * weights.exclusive as f64 / total,
+            100.0 * sum as f64 / total,
+            weights.inclusive,
+            100.0 * weights.inclusive as f64 / total,
+            html::escape(name)
+        );
+    }
+    page.push_str("</table>\n</body>\n</html>\n");
+    page
+}
+
+/// Call graph rendered in the browser by the Graphviz WebAssembly build, loaded from a CDN.
+fn graph(folded: &str, title: &str) -> Result<String> {
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{}</title>
+{STYLE}
+</head>
+<body>
+{NAV}
+<div id="graph"></div>
+<script src="https://cdn.jsdelivr.net/npm/@viz-js/viz@3.11.0/lib/viz-standalone.js"></script>
+<script>
+Viz.instance().then((viz) => {{
+  document.getElementById("graph").appendChild(viz.renderSVGElement({}));
+}});
+</script>
+</body>
+</html>
+"#,
+        html::escape(title),
+        html::script_json(&dot::call_graph(folded))?
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page() {
+        let folded = "main;foo;store_temp 10\nmain;function_call<user@foo> 2\nmain;return 1";
+        let top = page("/top", folded, "test").unwrap().unwrap();
+        let row = "<td>10</td><td>76.92%</td><td>76.92%</td><td>10</td><td>76.92%</td><td>foo</td>";
+        assert!(top.contains(row));
+        assert!(page("/graph?zoom=1", folded, "test").unwrap().is_some());
+        assert!(page("/missing", folded, "test").unwrap().is_none());
+    }
+}
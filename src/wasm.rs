@@ -0,0 +1,88 @@
+//! Bindings of the rendering for browser-based viewers, which load saved profiles client-side.
+//! Profiles are passed as the contents of a `.burnprofile`, a folded profile or an snforge trace.
+
+use inferno::flamegraph::{from_lines, Options};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+use crate::burnprofile;
+use crate::report::ProfileReport;
+use crate::{callgrind, chrome_trace, dot, gecko, html, snforge, speedscope, table};
+
+/// Title of the rendered profiles.
+const TITLE: &str = "scarb-burn";
+
+/// Folded stacks and the weight model of a saved profile.
+fn load(profile: &str) -> Result<(String, Option<String>), JsError> {
+    burnprofile::contents_folded(profile).map_err(|err| JsError::new(&format!("{err:#}")))
+}
+
+fn js_error(err: impl std::fmt::Display) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+/// Flamegraph SVG of the profile.
+#[wasm_bindgen]
+pub fn flamegraph(profile: &str) -> Result<String, JsError> {
+    let (folded, weight) = load(profile)?;
+    let mut options = Options::default();
+    if let Some(weight) = weight {
+        options.count_name = weight;
+    }
+    let mut svg = Vec::new();
+    from_lines(&mut options, folded.lines(), &mut svg).map_err(js_error)?;
+    String::from_utf8(svg).map_err(js_error)
+}
+
+/// Standalone HTML page with the interactive flamegraph of the profile.
+#[wasm_bindgen]
+pub fn html(profile: &str) -> Result<String, JsError> {
+    html::page(&load(profile)?.0, TITLE).map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn speedscope(profile: &str) -> Result<String, JsError> {
+    let profile = speedscope::profile(&load(profile)?.0, TITLE);
+    serde_json::to_string(&profile).map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn firefox(profile: &str) -> Result<String, JsError> {
+    serde_json::to_string(&gecko::profile(&load(profile)?.0, TITLE)).map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn chrome_trace(profile: &str) -> Result<String, JsError> {
+    serde_json::to_string(&chrome_trace::trace(&load(profile)?.0)).map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn callgrind(profile: &str) -> Result<String, JsError> {
+    Ok(callgrind::callgrind(&load(profile)?.0))
+}
+
+/// Call trace of the profile in the snforge format, read by cairo-profiler.
+#[wasm_bindgen]
+pub fn snforge_trace(profile: &str) -> Result<String, JsError> {
+    let (folded, weight) = load(profile)?;
+    serde_json::to_string(&snforge::trace(&folded, weight.as_deref())).map_err(js_error)
+}
+
+/// Graphviz call graph of the profile.
+#[wasm_bindgen]
+pub fn dot(profile: &str) -> Result<String, JsError> {
+    Ok(dot::call_graph(&load(profile)?.0))
+}
+
+/// Function table of the profile with the given delimiter, e.g. `,` for CSV.
+#[wasm_bindgen]
+pub fn table(profile: &str, delimiter: char) -> Result<String, JsError> {
+    Ok(table::table(&load(profile)?.0, delimiter))
+}
+
+/// JSON of the [`ProfileReport`] of the profile, as written by the `json` output type.
+#[wasm_bindgen]
+pub fn report(profile: &str) -> Result<String, JsError> {
+    let (folded, weight) = load(profile)?;
+    serde_json::to_string(&ProfileReport::new(&folded, weight, BTreeMap::new())).map_err(js_error)
+}